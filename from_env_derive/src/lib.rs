@@ -0,0 +1,403 @@
+//! Derive macro for `from_env::FromEnv`, see the docs of the `from_env` crate.
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    parse_macro_input, punctuated::Punctuated, Attribute, Data, DeriveInput, Expr, ExprLit, Fields,
+    Lit, LitChar, LitStr, Meta, Token,
+};
+
+#[proc_macro_derive(FromEnv, attributes(from_env))]
+pub fn derive_from_env(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "FromEnv can only be derived for structs with named fields",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "FromEnv can only be derived for structs with named fields",
+        ));
+    };
+    let container = ContainerAttrs::parse(&input.attrs)?;
+    let rename_all = serde_rename_all(&input.attrs)?;
+    let mut fields = Vec::new();
+    let mut names = Vec::new();
+    let mut referenced = Vec::new();
+    for field in &named.named {
+        let serde = SerdeField::parse(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field").to_string();
+        let ident = ident.strip_prefix("r#").unwrap_or(&ident);
+        let name = match serde.rename {
+            Some(name) => name,
+            None => rename_all.map_or(ident.to_string(), |rule| rule.apply(ident)),
+        };
+        let mut attrs = FieldAttrs::parse(&field.attrs)?;
+        if attrs.help.is_none() {
+            attrs.help = doc_comment(&field.attrs);
+        }
+        fields.push(attrs.to_tokens(&name));
+        referenced.extend(attrs.referenced);
+        names.push(name);
+    }
+    if let Some(unknown) = referenced.iter().find(|r| !names.contains(&r.value())) {
+        return Err(syn::Error::new_spanned(
+            unknown,
+            format!("no field named `{}`", unknown.value()),
+        ));
+    }
+
+    let check = container.validate.then(|| {
+        quote! {
+            fn check(&self) -> ::core::result::Result<(), ::from_env::Error> {
+                ::from_env::Validate::validate(self)
+            }
+        }
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::from_env::FromEnv for #ident #ty_generics #where_clause {
+            fn fields() -> &'static [::from_env::Field] {
+                const FIELDS: &[::from_env::Field] = &[#(#fields),*];
+                FIELDS
+            }
+
+            #check
+        }
+    })
+}
+
+/// The arguments of `#[from_env(...)]` on the struct.
+#[derive(Default)]
+struct ContainerAttrs {
+    validate: bool,
+}
+
+impl ContainerAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = ContainerAttrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("from_env")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("validate") {
+                    parsed.validate = true;
+                } else {
+                    return Err(meta.error(format!(
+                        "unknown attribute `{}`, expected `validate`",
+                        meta.path.to_token_stream()
+                    )));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// The arguments of `#[from_env(...)]` on a field.
+#[derive(Default)]
+struct FieldAttrs {
+    env: Option<LitStr>,
+    long: Option<LitStr>,
+    short: Option<LitChar>,
+    help: Option<String>,
+    default: Option<LitStr>,
+    secret: bool,
+    positional: bool,
+    subcommand: bool,
+    /// `::from_env::Constraint`s, in the order they were given.
+    constraints: Vec<TokenStream>,
+    /// `::from_env::Relation`s, in the order they were given.
+    relations: Vec<TokenStream>,
+    /// Names of other fields in relations, which need to exist.
+    referenced: Vec<LitStr>,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = FieldAttrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("from_env")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("env") {
+                    parsed.env = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("long") {
+                    parsed.long = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("short") {
+                    parsed.short = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("help") {
+                    parsed.help = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    parsed.default = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("secret") {
+                    parsed.secret = true;
+                } else if meta.path.is_ident("positional") {
+                    parsed.positional = true;
+                } else if meta.path.is_ident("subcommand") {
+                    parsed.subcommand = true;
+                } else if meta.path.is_ident("min") || meta.path.is_ident("max") {
+                    let bound: Expr = meta.value()?.parse()?;
+                    let constraint = if meta.path.is_ident("min") {
+                        quote!(Min)
+                    } else {
+                        quote!(Max)
+                    };
+                    parsed
+                        .constraints
+                        .push(quote!(::from_env::Constraint::#constraint((#bound) as f64)));
+                } else if meta.path.is_ident("non_empty") {
+                    parsed.constraints.push(quote!(::from_env::Constraint::NonEmpty));
+                } else if meta.path.is_ident("regex") {
+                    let pattern: LitStr = meta.value()?.parse()?;
+                    parsed
+                        .constraints
+                        .push(quote!(::from_env::Constraint::Regex(#pattern)));
+                } else if meta.path.is_ident("one_of") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let values = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                    let values = values.iter();
+                    parsed
+                        .constraints
+                        .push(quote!(::from_env::Constraint::OneOf(&[#(#values),*])));
+                } else if meta.path.is_ident("path_exists") {
+                    parsed.constraints.push(quote!(::from_env::Constraint::PathExists));
+                } else if meta.path.is_ident("requires") || meta.path.is_ident("conflicts_with") {
+                    let relation = if meta.path.is_ident("requires") {
+                        quote!(Requires)
+                    } else {
+                        quote!(ConflictsWith)
+                    };
+                    // `requires = "a"` or `requires("a", "b")`
+                    let others = if meta.input.peek(Token![=]) {
+                        vec![meta.value()?.parse::<LitStr>()?]
+                    } else {
+                        let content;
+                        syn::parenthesized!(content in meta.input);
+                        let others = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                        others.into_iter().collect()
+                    };
+                    for other in others {
+                        parsed
+                            .relations
+                            .push(quote!(::from_env::Relation::#relation(#other)));
+                        parsed.referenced.push(other);
+                    }
+                } else if meta.path.is_ident("required_if") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let other: LitStr = content.parse()?;
+                    content.parse::<Token![,]>()?;
+                    let value: LitStr = content.parse()?;
+                    parsed
+                        .relations
+                        .push(quote!(::from_env::Relation::RequiredIf(#other, #value)));
+                    parsed.referenced.push(other);
+                } else {
+                    return Err(meta.error(format!(
+                        "unknown attribute `{}`, expected one of `env`, `long`, `short`, `help`, `default`, `secret`, `positional`, `subcommand`, `min`, `max`, `non_empty`, `regex`, `one_of`, `path_exists`, `requires`, `conflicts_with`, `required_if`",
+                        meta.path.to_token_stream()
+                    )));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+
+    fn to_tokens(&self, name: &str) -> TokenStream {
+        let option = |value: Option<TokenStream>| match value {
+            Some(value) => quote!(::core::option::Option::Some(#value)),
+            None => quote!(::core::option::Option::None),
+        };
+        let env = option(self.env.as_ref().map(ToTokens::to_token_stream));
+        let long = option(self.long.as_ref().map(ToTokens::to_token_stream));
+        let short = option(self.short.as_ref().map(ToTokens::to_token_stream));
+        let help = option(self.help.as_ref().map(ToTokens::to_token_stream));
+        let default = option(self.default.as_ref().map(ToTokens::to_token_stream));
+        let secret = self.secret;
+        let positional = self.positional;
+        let subcommand = self.subcommand;
+        let constraints = &self.constraints;
+        let relations = &self.relations;
+        quote! {
+            ::from_env::Field {
+                env: #env,
+                long: #long,
+                short: #short,
+                help: #help,
+                default: #default,
+                secret: #secret,
+                positional: #positional,
+                subcommand: #subcommand,
+                constraints: &[#(#constraints),*],
+                relations: &[#(#relations),*],
+                ..::from_env::Field::new(#name)
+            }
+        }
+    }
+}
+
+/// The serde attributes of a field that change which key it is read from.
+#[derive(Default)]
+struct SerdeField {
+    rename: Option<String>,
+    skip: bool,
+}
+
+impl SerdeField {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = SerdeField::default();
+        for meta in serde_metas(attrs)? {
+            if meta.path().is_ident("rename") {
+                parsed.rename = deserialize_name(&meta)?;
+            } else if meta.path().is_ident("skip") || meta.path().is_ident("skip_deserializing") {
+                parsed.skip = true;
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn serde_rename_all(attrs: &[Attribute]) -> syn::Result<Option<RenameRule>> {
+    for meta in serde_metas(attrs)? {
+        if meta.path().is_ident("rename_all") {
+            if let Some(rule) = deserialize_name(&meta)? {
+                return RenameRule::parse(&rule)
+                    .map(Some)
+                    .ok_or_else(|| syn::Error::new_spanned(&meta, "unknown rename rule"));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn serde_metas(attrs: &[Attribute]) -> syn::Result<Vec<Meta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        metas.extend(attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?);
+    }
+    Ok(metas)
+}
+
+/// The name given by `rename = "name"` or `rename(deserialize = "name")`.
+fn deserialize_name(meta: &Meta) -> syn::Result<Option<String>> {
+    match meta {
+        Meta::NameValue(nv) => Ok(lit_str(&nv.value)),
+        Meta::List(list) => {
+            let nested = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+            Ok(nested.iter().find_map(|meta| match meta {
+                Meta::NameValue(nv) if nv.path.is_ident("deserialize") => lit_str(&nv.value),
+                _ => None,
+            }))
+        }
+        Meta::Path(_) => Ok(None),
+    }
+}
+
+fn lit_str(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) => Some(s.value()),
+        _ => None,
+    }
+}
+
+/// The first paragraph of the doc comment, joined into one line.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let mut lines = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("doc")) {
+        let Meta::NameValue(nv) = &attr.meta else {
+            continue;
+        };
+        let Some(line) = lit_str(&nv.value) else {
+            continue;
+        };
+        let line = line.trim().to_string();
+        if line.is_empty() {
+            if lines.is_empty() {
+                continue;
+            }
+            break;
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
+
+/// The rules of `#[serde(rename_all = "...")]`, applied to snake_case field names.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &str) -> Option<Self> {
+        Some(match rule {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return None,
+        })
+    }
+
+    fn apply(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal | RenameRule::Camel => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(c);
+                    }
+                }
+                let mut chars = pascal.chars();
+                match (self, chars.next()) {
+                    (RenameRule::Camel, Some(first)) => {
+                        first.to_ascii_lowercase().to_string() + chars.as_str()
+                    }
+                    _ => pascal,
+                }
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
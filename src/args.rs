@@ -0,0 +1,470 @@
+//! Parser for CLI args.
+//!
+//! Supports GNU-style syntax:
+//! - `--key value`, `--key=value` and, for bool fields, `--flag` alone, which gives `true`
+//! - short flags declared with `#[from_env(short = 'p')]`: `-p value` and `-pvalue`
+//! - bundled short flags of bool fields like `-vq`, optionally ending in one that takes a value
+//! - `--no-flag` to set a bool field to `false`
+//! - `--` to end the options
+//!
+//! Args without flag populate the `positional` fields in order, after the first one chose the
+//! variant of the `subcommand` field, if there is one.
+
+use crate::{
+    de::parse_bool,
+    key_file, raw,
+    schema::{Schema, Variant},
+    Error, Field, Origin, Raw,
+};
+
+/// The flag overriding the path of the `.env` file, see [`Options::dotenv_override`](crate::Options::dotenv_override).
+pub(crate) const ENV_FILE_FLAG: &str = "env-file";
+
+/// The flag printing the `.env` template and exiting, see
+/// [`FromEnvBuilder::env_template`](crate::FromEnvBuilder::env_template).
+pub(crate) const ENV_TEMPLATE_FLAG: &str = "print-env-template";
+
+/// The flag giving the path of the config file, see [`Options::config_path`](crate::Options::config_path).
+pub(crate) const CONFIG_FLAG: &str = "config";
+
+/// The value given with the last `--flag value` or `--flag=value` before `--`, for flags that
+/// configure the sources instead of populating fields.
+pub(crate) fn reserved_value(
+    args: &[String],
+    flag: &str,
+    fields: &[Field],
+    schema: &Schema,
+) -> Option<String> {
+    let mut path = None;
+    let mut args = args.iter().take_while(|a| *a != "--");
+    while let Some(a) = args.next() {
+        let Some(long) = a.strip_prefix("--") else {
+            continue;
+        };
+        let (name, inline) = match long.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (long, None),
+        };
+        let name = trim_quotes(name);
+        if name == flag && is_reserved_flag(name, fields, schema) {
+            path = inline
+                .or_else(|| args.next().map(String::as_str))
+                .map(trim_quotes);
+        }
+    }
+    path.map(str::to_string)
+}
+
+/// Repeated keys like `--host a --host b` are kept in order. Flags of fields other than bools
+/// need a value. With `strict`, undeclared short flags and args left over by the positional
+/// fields are errors; unknown long flags are left to deserializing.
+pub(crate) fn kv_from_args(
+    args: &[String],
+    fields: &[Field],
+    schema: &Schema,
+    strict: bool,
+) -> Result<Vec<(String, Raw)>, Error> {
+    let is_bool = |key: &str| schema.get(key).is_some_and(Schema::is_bool);
+    let takes_value = |key: &str| {
+        schema
+            .get(key)
+            .is_some_and(|s| !s.is_bool() && *s.unwrap_optional() != Schema::Any)
+    };
+    let missing = |key: &str, origin: Origin| Error::Syntax {
+        origin,
+        message: format!("missing value for `{key}`"),
+    };
+    let mut errors = Vec::new();
+    let subcommand = fields.iter().find(|f| f.subcommand);
+    let mut positionals = fields
+        .iter()
+        .filter(|f| f.positional && !f.subcommand)
+        .peekable();
+    // path of the chosen variant, like `command.serve`
+    let mut variant: Option<String> = None;
+    // keys of flags after the subcommand are looked up in its variant first
+    let scoped = |key: String, variant: &Option<String>| match variant {
+        Some(variant) if schema.get(&format!("{variant}.{key}")).is_some() => {
+            format!("{variant}.{key}")
+        }
+        _ => key,
+    };
+    // keys are `None` for flags that are not read, see `arg_key`
+    let mut kv: Vec<(Option<String>, Raw)> = Vec::new();
+    let mut options_ended = false;
+    let mut i = 0;
+    while i < args.len() {
+        let a = &args[i];
+        i += 1;
+        // the program name is 0
+        let index = i;
+        if a == "--" && !options_ended {
+            options_ended = true;
+            continue;
+        }
+        if strict && !options_ended {
+            if let Some(c) = undeclared_short_flag(a, fields) {
+                errors.push(unknown_short(c, index));
+                continue;
+            }
+        }
+        if options_ended || !(a.starts_with("--") || is_short_flag(a, fields)) {
+            let origin = Origin::Positional { index };
+            if let (Some(field), None) = (subcommand, &variant) {
+                let chosen = match schema.get(field.name).map(Schema::unwrap_optional) {
+                    Some(Schema::Enum(variants)) => Variant::find(variants, a),
+                    _ => None,
+                };
+                variant = Some(format!(
+                    "{}.{}",
+                    field.name,
+                    chosen.map_or(a.as_str(), |v| v.name)
+                ));
+                kv.push((Some(field.name.to_string()), raw(a, origin)));
+            } else if let Some(field) = positionals.peek() {
+                kv.push((Some(field.name.to_string()), raw(a, origin)));
+                // a sequence takes all remaining args
+                if !matches!(
+                    schema.get(field.name).map(Schema::unwrap_optional),
+                    Some(Schema::List(_))
+                ) {
+                    positionals.next();
+                }
+            } else if strict {
+                errors.push(Error::Unknown {
+                    key: a.clone(),
+                    origin,
+                    suggestion: None,
+                });
+            } else {
+                // ignore values without key
+            }
+            continue;
+        }
+        if let Some(long) = a.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (trim_quotes(name), Some(value)),
+                None => (trim_quotes(long), None),
+            };
+            let flag = format!("--{name}");
+            if is_reserved_flag(name, fields, schema) {
+                if inline.is_none() {
+                    take_value(args, &mut i, false, fields, flag);
+                }
+                continue;
+            }
+            if let (Some(key), None) = (negated_bool(name, fields, schema, &variant), inline) {
+                kv.push((Some(key), raw("false", Origin::Arg { index, flag })));
+                continue;
+            }
+            let key = match arg_key(name, fields) {
+                Ok(key) => Some(scoped(key, &variant)),
+                Err(explicit) => {
+                    if strict {
+                        let origin = Origin::Arg {
+                            index,
+                            flag: flag.clone(),
+                        };
+                        errors.push(crate::renamed(name, origin, explicit));
+                    }
+                    None
+                }
+            };
+            let bool_flag = key.as_deref().is_some_and(is_bool);
+            let (value, origin) = match inline {
+                Some(value) => (Some(value), Origin::Arg { index, flag }),
+                None => take_value(args, &mut i, bool_flag, fields, flag),
+            };
+            let value = match (value, key.as_deref()) {
+                (Some(value), _) => value,
+                (None, Some(key)) if takes_value(key) => {
+                    errors.push(missing(key, origin));
+                    continue;
+                }
+                (None, _) => "true",
+            };
+            kv.push((key, raw(trim_quotes(value), origin)));
+        } else {
+            let shorts = &a[1..];
+            for (pos, c) in shorts.char_indices() {
+                // the rest of a bundle after an unknown flag is ignored
+                let Some(field) = Field::by_short(fields, c) else {
+                    if strict {
+                        errors.push(unknown_short(c, index));
+                    }
+                    break;
+                };
+                let key = field.name.to_string();
+                let flag = format!("-{c}");
+                if is_bool(&key) {
+                    kv.push((Some(key), raw("true", Origin::Arg { index, flag })));
+                    continue;
+                }
+                let rest = &shorts[pos + c.len_utf8()..];
+                let (value, origin) = if rest.is_empty() {
+                    take_value(args, &mut i, false, fields, flag)
+                } else {
+                    (Some(rest), Origin::Arg { index, flag })
+                };
+                match value {
+                    Some(value) => kv.push((Some(key), raw(trim_quotes(value), origin))),
+                    None if takes_value(&key) => errors.push(missing(&key, origin)),
+                    None => kv.push((Some(key), raw("true", origin))),
+                }
+                break;
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(Error::from_many(errors));
+    }
+    Ok(kv.into_iter().filter_map(|(k, v)| Some((k?, v))).collect())
+}
+
+/// Whether `--name` is `--env-file` or `--config` and not taken by a field.
+fn is_reserved_flag(name: &str, fields: &[Field], schema: &Schema) -> bool {
+    [ENV_FILE_FLAG, CONFIG_FLAG].contains(&name)
+        && arg_key(name, fields)
+            .ok()
+            .is_none_or(|key| schema.get(&key).is_none())
+}
+
+/// Takes the arg at `i` as the value of a flag, if it is not a flag itself. Flags of bool fields
+/// only take `true`, `false` and the like, so `--verbose input.txt` leaves `input.txt` alone.
+///
+/// `None` if the flag has no value. `i` is the index of the arg after the flag, counting from 0.
+fn take_value<'a>(
+    args: &'a [String],
+    i: &mut usize,
+    bool_flag: bool,
+    fields: &[Field],
+    flag: String,
+) -> (Option<&'a str>, Origin) {
+    match args.get(*i) {
+        Some(next) if bool_flag && parse_bool(next).is_none() => {}
+        Some(next) if next.starts_with("--") || is_short_flag(next, fields) => {}
+        Some(next) => {
+            *i += 1;
+            // the program name is 0
+            return (Some(next), Origin::Arg { index: *i, flag });
+        }
+        None => {}
+    }
+    (None, Origin::Arg { index: *i, flag })
+}
+
+/// The key for the flag `--key`, taking the explicit `long` names of fields into account. `Err`
+/// with the explicit name if the key belongs to a field that is read from another flag.
+fn arg_key(key: &str, fields: &[Field]) -> Result<String, String> {
+    let explicit = |f: &Field| f.long;
+    if let Some(field) = Field::by_long(fields, key) {
+        Ok(field.name.to_string())
+    } else if let Some(key) = key_file::arg_key(fields, key) {
+        Ok(key)
+    } else if let Some(name) = Field::renamed(fields, key, explicit) {
+        Err(name.to_string())
+    } else if let Some(name) = key_file::renamed(fields, key, explicit) {
+        Err(name)
+    } else {
+        Ok(key.to_string())
+    }
+}
+
+/// The key of the bool field turned off by `--no-key` or `--no_key`, unless that is a field itself.
+/// Fields of the chosen `variant` of a subcommand come first.
+fn negated_bool(
+    name: &str,
+    fields: &[Field],
+    schema: &Schema,
+    variant: &Option<String>,
+) -> Option<String> {
+    let candidates = |key: String| {
+        let scoped = variant.as_ref().map(|variant| format!("{variant}.{key}"));
+        scoped.into_iter().chain([key])
+    };
+    let is_field = |key: &String| schema.get(key).is_some();
+    if arg_key(name, fields)
+        .ok()
+        .into_iter()
+        .flat_map(candidates)
+        .any(|k| is_field(&k))
+    {
+        return None;
+    }
+    let flag = name
+        .strip_prefix("no-")
+        .or_else(|| name.strip_prefix("no_"))?;
+    let key = arg_key(flag, fields).ok()?;
+    candidates(key).find(|key| schema.get(key).is_some_and(Schema::is_bool))
+}
+
+/// Whether `arg` starts with a declared short flag, like `-d` or `-vq`. Other args starting with
+/// `-`, like negative numbers, are values.
+fn is_short_flag(arg: &str, fields: &[Field]) -> bool {
+    arg.strip_prefix('-')
+        .and_then(|s| s.chars().next())
+        .is_some_and(|c| Field::by_short(fields, c).is_some())
+}
+
+/// The flag of `arg` if it looks like a short flag, like `-x`, but none was declared. Negative
+/// numbers are not flags.
+fn undeclared_short_flag(arg: &str, fields: &[Field]) -> Option<char> {
+    let c = arg.strip_prefix('-')?.chars().next()?;
+    (c.is_alphabetic() && Field::by_short(fields, c).is_none()).then_some(c)
+}
+
+fn unknown_short(c: char, index: usize) -> Error {
+    Error::Unknown {
+        key: c.to_string(),
+        origin: Origin::Arg {
+            index,
+            flag: format!("-{c}"),
+        },
+        suggestion: None,
+    }
+}
+
+fn trim_quotes(s: &str) -> &str {
+    s.trim_matches('\'').trim_matches('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Args {
+        port: u16,
+        offset: i32,
+        verbose: bool,
+        quiet: bool,
+        name: Option<String>,
+        files: Vec<String>,
+    }
+
+    const FIELDS: &[Field] = &[
+        Field {
+            short: Some('p'),
+            ..Field::new("port")
+        },
+        Field::new("offset"),
+        Field {
+            short: Some('v'),
+            ..Field::new("verbose")
+        },
+        Field {
+            short: Some('q'),
+            ..Field::new("quiet")
+        },
+        Field::new("name"),
+        Field {
+            positional: true,
+            ..Field::new("files")
+        },
+    ];
+
+    fn kv(args: &[&str]) -> Result<Vec<(String, String)>, Error> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let kv = kv_from_args(&args, FIELDS, &Schema::of::<Args>(), false)?;
+        Ok(kv.into_iter().map(|(k, raw)| (k, raw.value)).collect())
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn long_flags() {
+        assert_eq!(
+            kv(&["--port=80", "--name", "x", "--verbose"]).unwrap(),
+            pairs(&[("port", "80"), ("name", "x"), ("verbose", "true")])
+        );
+        assert_eq!(kv(&["--name=a=b"]).unwrap(), pairs(&[("name", "a=b")]));
+    }
+
+    #[test]
+    fn short_flags() {
+        assert_eq!(kv(&["-p8080"]).unwrap(), pairs(&[("port", "8080")]));
+        assert_eq!(kv(&["-p", "8080"]).unwrap(), pairs(&[("port", "8080")]));
+    }
+
+    #[test]
+    fn bundled_short_flags() {
+        assert_eq!(
+            kv(&["-vq"]).unwrap(),
+            pairs(&[("verbose", "true"), ("quiet", "true")])
+        );
+        assert_eq!(
+            kv(&["-vp8080"]).unwrap(),
+            pairs(&[("verbose", "true"), ("port", "8080")])
+        );
+        assert_eq!(
+            kv(&["-vp", "8080"]).unwrap(),
+            pairs(&[("verbose", "true"), ("port", "8080")])
+        );
+    }
+
+    #[test]
+    fn negated_bools() {
+        assert_eq!(
+            kv(&["--no-verbose"]).unwrap(),
+            pairs(&[("verbose", "false")])
+        );
+        assert_eq!(kv(&["--no_quiet"]).unwrap(), pairs(&[("quiet", "false")]));
+        // bool flags leave values that are not bools to the positional fields
+        assert_eq!(
+            kv(&["--verbose", "false", "--quiet", "a.txt"]).unwrap(),
+            pairs(&[("verbose", "false"), ("quiet", "true"), ("files", "a.txt")])
+        );
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        assert_eq!(
+            kv(&["a.txt", "--", "-p", "--port"]).unwrap(),
+            pairs(&[("files", "a.txt"), ("files", "-p"), ("files", "--port")])
+        );
+    }
+
+    #[test]
+    fn negative_numbers_are_values() {
+        assert_eq!(kv(&["--offset", "-5"]).unwrap(), pairs(&[("offset", "-5")]));
+        assert_eq!(kv(&["-5"]).unwrap(), pairs(&[("files", "-5")]));
+    }
+
+    #[test]
+    fn flags_without_value() {
+        let e = kv(&["-p"]).unwrap_err();
+        assert_eq!(e.to_string(), "CLI arg -p: missing value for `port`");
+        let e = kv(&["--port", "--verbose"]).unwrap_err();
+        assert_eq!(e.to_string(), "CLI arg --port: missing value for `port`");
+        let e = kv(&["-vp"]).unwrap_err();
+        assert_eq!(e.to_string(), "CLI arg -p: missing value for `port`");
+        assert_eq!(kv(&["--verbose"]).unwrap(), pairs(&[("verbose", "true")]));
+    }
+
+    #[test]
+    fn strict_rejects_unclaimed_args() {
+        let args = ["--name", "x", "-x", "a.txt", "-vx"].map(String::from);
+        let schema = Schema::of::<Args>();
+        assert!(kv_from_args(&args, FIELDS, &schema, false).is_ok());
+        let e = kv_from_args(&args, FIELDS, &schema, true).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "2 errors:\n  unknown key `x` (CLI arg -x)\n  unknown key `x` (CLI arg -x)"
+        );
+        let fields = &FIELDS[..FIELDS.len() - 1];
+        let args = ["a.txt", "--", "-5"].map(String::from);
+        let e = kv_from_args(&args, fields, &schema, true).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "2 errors:\n  unknown key `a.txt` (CLI arg 1)\n  unknown key `-5` (CLI arg 3)"
+        );
+    }
+}
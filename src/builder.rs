@@ -0,0 +1,344 @@
+use std::{
+    io::{self, Write},
+    marker::PhantomData,
+    path::PathBuf,
+    process,
+    time::Duration,
+};
+
+use crate::{schema::Schema, Error, FromEnv, Options, Provenance, Source, Watched};
+
+/// Configures the sources of a [`FromEnv`](crate::FromEnv) type, created by
+/// [`FromEnv::builder`](crate::FromEnv::builder).
+///
+#[cfg_attr(feature = "derive", doc = "```no_run")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use from_env::FromEnv;
+/// # #[derive(serde::Deserialize, FromEnv)]
+/// # struct Constants { server_url: String }
+/// let constants = Constants::builder()
+///     .dotenv("config/.env.prod")
+///     .env_prefix("APP_")
+///     .args(["--server_url", "localhost:8080"])
+///     .build()?;
+/// # Ok::<(), from_env::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FromEnvBuilder<T> {
+    options: Options,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> FromEnvBuilder<T> {
+    /// A builder with the default [`Options`].
+    pub fn new() -> Self {
+        Self::from(Options::default())
+    }
+
+    /// The sources to read from, from lowest to highest precedence. Sources left out are not read.
+    ///
+    /// Defaults to `[Source::File, Source::Dotenv, Source::Env, Source::Args]`.
+    pub fn sources(mut self, sources: impl IntoIterator<Item = Source>) -> Self {
+        self.options.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// Path of the `.env` file, defaults to `.env` in the current directory.
+    pub fn dotenv(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.dotenv_path = path.into();
+        self
+    }
+
+    /// Content of the `.env` file to read instead of the file itself.
+    pub fn dotenv_str(mut self, src: impl Into<String>) -> Self {
+        self.options.dotenv_content = Some(src.into());
+        self
+    }
+
+    /// Environment variables to read instead of the ones of the process.
+    pub fn env_vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let vars = vars.into_iter().map(|(k, v)| (k.into(), v.into()));
+        self.options.env_vars = Some(vars.collect());
+        self
+    }
+
+    /// See [`Options::config_path`].
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.config_path = Some(path.into());
+        self
+    }
+
+    /// See [`Options::key_files`].
+    pub fn key_files(mut self, read: bool) -> Self {
+        self.options.key_files = read;
+        self
+    }
+
+    /// See [`Options::search_parents`].
+    pub fn search_parents(mut self, search: bool) -> Self {
+        self.options.search_parents = search;
+        self
+    }
+
+    /// See [`Options::dotenv_override`].
+    pub fn dotenv_override(mut self, allow: bool) -> Self {
+        self.options.dotenv_override = allow;
+        self
+    }
+
+    /// See [`Options::dotenv_layers`].
+    pub fn dotenv_layers(mut self, layers: bool) -> Self {
+        self.options.dotenv_layers = layers;
+        self
+    }
+
+    /// See [`Options::app_env`].
+    pub fn app_env(mut self, app_env: impl Into<String>) -> Self {
+        self.options.app_env = Some(app_env.into());
+        self
+    }
+
+    /// See [`Options::env_prefix`].
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.options.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// CLI args to read instead of [`std::env::args`], without the program name.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.args = Some(args.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// See [`Options::separator`].
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.options.separator = separator.into();
+        self
+    }
+
+    /// See [`Options::list_delimiter`].
+    pub fn list_delimiter(mut self, delimiter: char) -> Self {
+        self.options.list_delimiter = delimiter;
+        self
+    }
+
+    /// See [`Options::pair_delimiter`].
+    pub fn pair_delimiter(mut self, delimiter: char) -> Self {
+        self.options.pair_delimiter = delimiter;
+        self
+    }
+
+    /// See [`Options::expand_single_quoted`].
+    pub fn expand_single_quoted(mut self, expand: bool) -> Self {
+        self.options.expand_single_quoted = expand;
+        self
+    }
+
+    /// See [`Options::help`].
+    pub fn help(mut self, help: bool) -> Self {
+        self.options.help = help;
+        self
+    }
+
+    /// See [`Options::strict`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    /// The options set so far.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+}
+
+impl<T: FromEnv> FromEnvBuilder<T> {
+    /// Reads the sources into `T`.
+    ///
+    /// If the CLI args contain `--help` or `-h`, prints [`Self::usage`] and exits the process
+    /// instead, unless turned off with [`Self::help`].
+    pub fn build(&self) -> Result<T, Error> {
+        self.build_with_report().map(|(value, _)| value)
+    }
+
+    /// Like [`Self::build`], also telling where each value came from.
+    pub fn build_with_report(&self) -> Result<(T, Provenance), Error> {
+        if self.options.help
+            && self.options.sources.contains(&Source::Args)
+            && crate::help::requested(&crate::cli_args(&self.options), T::fields())
+        {
+            print!("{}", self.usage());
+            let _ = io::stdout().flush();
+            process::exit(0);
+        }
+        if self.options.help
+            && self.options.sources.contains(&Source::Args)
+            && crate::help::template_requested(&crate::cli_args(&self.options), T::fields())
+        {
+            print!("{}", self.env_template());
+            let _ = io::stdout().flush();
+            process::exit(0);
+        }
+        let schema = Schema::of::<T>();
+        let node = crate::kv_from_sources(&self.options, T::fields(), &schema)?;
+        let violations = crate::validate::check(&node, &schema, T::fields(), &self.options);
+        let value = match crate::de::deserialize::<T>(&node, &self.options) {
+            Ok(value) if violations.is_empty() => value,
+            Ok(_) => return Err(Error::from_many(violations)),
+            Err(e) => {
+                // values that did not parse are not checked
+                let mut errors = match e.redacted_secrets(T::fields()) {
+                    Error::Multiple(errors) => errors,
+                    e => vec![e],
+                };
+                let failed: Vec<_> = errors
+                    .iter()
+                    .filter_map(Error::key)
+                    .map(str::to_string)
+                    .collect();
+                errors.extend(
+                    violations
+                        .into_iter()
+                        .filter(|v| !failed.iter().any(|k| v.key() == Some(k))),
+                );
+                return Err(Error::from_many(errors));
+            }
+        };
+        value.check()?;
+        let provenance = Provenance::new(&node, &schema, T::fields(), self.options.list_delimiter);
+        Ok((value, provenance))
+    }
+
+    /// The `.env` files [`Self::build`] reads, from lowest to highest precedence, taking
+    /// `--env-file`, `DOTENV_PATH`, [`Self::search_parents`] and [`Self::dotenv_layers`] into
+    /// account. Empty if there are none, or if the content was given with [`Self::dotenv_str`].
+    ///
+    #[cfg_attr(feature = "derive", doc = "```no_run")]
+    #[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+    /// # use from_env::FromEnv;
+    /// # #[derive(serde::Deserialize, FromEnv)]
+    /// # struct Constants { server_url: String }
+    /// let builder = Constants::builder().search_parents(true).dotenv_layers(true);
+    /// for path in builder.dotenv_files()? {
+    ///     eprintln!("reading {}", path.display());
+    /// }
+    /// let constants = builder.build()?;
+    /// # Ok::<(), from_env::Error>(())
+    /// ```
+    pub fn dotenv_files(&self) -> Result<Vec<PathBuf>, Error> {
+        if self.options.dotenv_content.is_some() || !self.options.sources.contains(&Source::Dotenv)
+        {
+            return Ok(Vec::new());
+        }
+        crate::dotenv_files(&self.options, T::fields(), &Schema::of::<T>())
+    }
+
+    /// Reads the sources into `T`, and again each time the `.env` or config files change, which
+    /// is checked every `interval`. See [`Watched`].
+    pub fn watch(self, interval: Duration) -> Result<Watched<T>, Error>
+    where
+        T: Send + Sync + 'static,
+    {
+        Watched::new(self, interval)
+    }
+
+    /// A table of all keys `T` accepts, with their CLI flag, type, default, environment variable
+    /// and description:
+    ///
+    /// ```txt
+    /// Usage: server [OPTIONS]
+    ///
+    ///   OPTION               TYPE    DEFAULT         ENV             DESCRIPTION
+    ///   -d, --db             string  required        DATABASE_URL    Connection string of the main database.
+    ///       --server_url     string  127.0.0.1:8080  SERVER_URL      Address to listen on
+    ///       --database.port  u16                     DATABASE__PORT
+    ///   -h, --help                                                   Print this help
+    /// ```
+    ///
+    /// Types and whether fields are required are found by deserializing `T` from a stand-in that
+    /// records what it asks for, so they reflect the actual [`serde::Deserialize`] implementation.
+    pub fn usage(&self) -> String {
+        crate::help::usage(&Schema::of::<T>(), T::fields(), &self.options)
+    }
+
+    /// A `.env` file listing all keys `T` accepts, each with its description, type and default,
+    /// to check in as `.env.example`. Keys are named as in `.env` files, without
+    /// [`Self::env_prefix`]. Only required keys are left uncommented:
+    ///
+    /// ```txt
+    /// # Connection string of the main database.
+    /// # string, required
+    /// DATABASE_URL=
+    ///
+    /// # Address to listen on
+    /// # string, default 127.0.0.1:8080
+    /// # SERVER_URL=127.0.0.1:8080
+    /// ```
+    ///
+    /// If the CLI args contain `--print-env-template`, [`Self::build`] prints it and exits the
+    /// process, unless turned off with [`Self::help`].
+    pub fn env_template(&self) -> String {
+        crate::help::env_template(&Schema::of::<T>(), T::fields(), &self.options)
+    }
+
+    /// A JSON Schema (draft 2020-12) of the values `T` accepts, in the shape of a JSON config
+    /// file, with types, defaults, required fields, enum variants, nested objects and the
+    /// [`Constraint`](crate::Constraint)s and [`Relation`](crate::Relation)s of the fields.
+    ///
+    /// Each key also has the names it is given by in the other sources, as the extensions `x-env`,
+    /// `x-cli` and `x-cli-short`. Secrets are marked with `x-secret` and their defaults left out.
+    ///
+    /// ```
+    /// # use from_env::FromEnv;
+    /// #[derive(serde::Deserialize, FromEnv)]
+    /// struct Constants {
+    ///     #[from_env(default = "8080", short = 'p')]
+    ///     port: u16,
+    /// }
+    ///
+    /// let schema = Constants::builder().json_schema();
+    /// assert_eq!(
+    ///     schema["properties"]["port"],
+    ///     serde_json::json!({
+    ///         "type": "integer",
+    ///         "minimum": 0,
+    ///         "default": 8080,
+    ///         "x-env": "PORT",
+    ///         "x-cli": "--port",
+    ///         "x-cli-short": "-p",
+    ///     })
+    /// );
+    /// ```
+    #[cfg(feature = "schema")]
+    pub fn json_schema(&self) -> serde_json::Value {
+        // the name without path, like `Constants`
+        let name = std::any::type_name::<T>();
+        let name = name.split('<').next().unwrap_or(name);
+        let title = name.rsplit("::").next().unwrap_or(name);
+        crate::json_schema::json_schema(&Schema::of::<T>(), T::fields(), &self.options, title)
+    }
+}
+
+impl<T> Default for FromEnvBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Options> for FromEnvBuilder<T> {
+    fn from(options: Options) -> Self {
+        FromEnvBuilder {
+            options,
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,1030 @@
+//! A [`serde::Deserializer`] over the collected values.
+//!
+//! Values are only parsed once the target type asks for them, so `"1"` becomes a `u16` for a
+//! `u16` field and stays `"1"` for a `String` field.
+
+use std::collections::BTreeSet;
+
+use serde::de::{
+    self,
+    value::{MapDeserializer, SeqDeserializer},
+    DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+
+use crate::{
+    node::{Node, Raw},
+    secret, Error, Options, Origin,
+};
+
+/// Deserializes `T`, carrying on after errors to report all missing and invalid fields at once.
+///
+/// After a value fails, deserialization starts over without it, and if it turns out to be
+/// required, with a placeholder in its place. This ends once everything else deserializes, or a
+/// placeholder is not accepted by its type.
+pub(crate) fn deserialize<T: DeserializeOwned>(node: &Node, options: &Options) -> Result<T, Error> {
+    let mut errors = Vec::new();
+    let mut failed = Failed::default();
+    loop {
+        let de = NodeDeserializer {
+            node: node.clone(),
+            options,
+            path: String::new(),
+            failed: &failed,
+        };
+        let e = match T::deserialize(de) {
+            Ok(value) if errors.is_empty() => return Ok(value),
+            Ok(_) => return Err(Error::from_many(errors)),
+            Err(e) => e,
+        };
+        let in_placeholder = |key: &str| {
+            failed
+                .placeholders
+                .iter()
+                .any(|p| key == p || key.starts_with(&format!("{p}.")))
+        };
+        match e.key() {
+            Some(key) if failed.omitted.contains(key) => {
+                // missing because it was omitted, the error was already recorded
+                let key = key.to_string();
+                failed.omitted.remove(&key);
+                failed.placeholders.insert(key);
+            }
+            Some(key) if !in_placeholder(key) => {
+                let key = key.to_string();
+                if matches!(e, Error::Missing { .. }) {
+                    failed.placeholders.insert(key);
+                } else {
+                    failed.omitted.insert(key);
+                }
+                errors.push(e);
+            }
+            // errors caused by placeholders are not interesting
+            _ => {
+                if errors.is_empty() {
+                    errors.push(e);
+                }
+                return Err(Error::from_many(errors));
+            }
+        }
+    }
+}
+
+/// Paths of values that already failed, see [`deserialize`].
+#[derive(Default)]
+struct Failed {
+    omitted: BTreeSet<String>,
+    placeholders: BTreeSet<String>,
+}
+
+/// Deserializes a struct or map from the children of a [`Node`], or anything else from its values.
+struct NodeDeserializer<'a> {
+    node: Node,
+    options: &'a Options,
+    /// Path of the node, in the same form as the keys of [`Error`]s.
+    path: String,
+    failed: &'a Failed,
+}
+
+impl<'a> NodeDeserializer<'a> {
+    fn child(&self, segment: &str, node: Node) -> Self {
+        let path = if self.path.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{}.{segment}", self.path)
+        };
+        NodeDeserializer {
+            node,
+            options: self.options,
+            path,
+            failed: self.failed,
+        }
+    }
+
+    /// A child with a single value, for the items of lists and maps given as one string.
+    fn child_value(&self, segment: &str, value: String, origin: &Origin) -> Self {
+        let node = Node {
+            values: vec![Raw {
+                value,
+                origin: origin.clone(),
+            }],
+            ..Default::default()
+        };
+        self.child(segment, node)
+    }
+
+    fn is_placeholder(&self) -> bool {
+        self.failed.placeholders.contains(&self.path)
+    }
+
+    fn is_omitted(&self) -> bool {
+        self.failed.omitted.contains(&self.path)
+    }
+
+    /// An [`Error::Unknown`] for this node, given as `key` to a struct with `fields` that do not
+    /// include it, if strict mode checks the source of one of its values.
+    fn unknown(&self, key: &str, fields: &[&str]) -> Option<Error> {
+        if !self.options.strict {
+            return None;
+        }
+        let checked = |raw: &Raw| match raw.origin {
+            Origin::File { .. }
+            | Origin::KeyFile { .. }
+            | Origin::Dotenv { .. }
+            | Origin::Arg { .. }
+            | Origin::Positional { .. } => true,
+            Origin::Env { .. } => self.options.env_prefix.is_some(),
+            Origin::Default => false,
+        };
+        let raw = self.node.find_value(&checked)?;
+        Some(Error::Unknown {
+            key: key.to_string(),
+            origin: raw.origin.clone(),
+            suggestion: closest(key, fields).map(str::to_string),
+        })
+    }
+
+    fn into_raw(mut self, visitor: &dyn de::Expected) -> Result<Raw, Error> {
+        match self.node.values.pop() {
+            Some(raw) => Ok(raw),
+            None => Err(de::Error::invalid_type(Unexpected::Map, visitor)),
+        }
+    }
+
+    /// Splits the values at the list delimiter, so `a,b` and `--host a --host b` both give two items.
+    fn split_values(&self) -> Vec<(String, &Origin)> {
+        self.node
+            .values
+            .iter()
+            .filter(|raw| !raw.value.is_empty())
+            .flat_map(|raw| {
+                raw.value
+                    .split(self.options.list_delimiter)
+                    .map(|item| (item.to_string(), &raw.origin))
+            })
+            .collect()
+    }
+}
+
+macro_rules! deserialize_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                if self.is_placeholder() {
+                    return Placeholder.$method(visitor);
+                }
+                let raw = self.into_raw(&visitor)?;
+                ValueDeserializer(raw.value.clone())
+                    .$method(visitor)
+                    .map_err(|e| e.with_value(&raw.value, &raw.origin))
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for NodeDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_placeholder() {
+            return Placeholder.deserialize_any(visitor);
+        }
+        if self.node.children.is_empty() && !self.node.values.is_empty() {
+            let raw = self.into_raw(&visitor)?;
+            visitor.visit_string(raw.value)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    deserialize_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_placeholder() {
+            return Placeholder.deserialize_option(visitor);
+        }
+        if self.node.children.is_empty() && self.node.value().is_some_and(|v| v.value.is_empty()) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.is_placeholder() {
+            return Placeholder.deserialize_unit_struct(name, visitor);
+        }
+        let raw = self.into_raw(&visitor)?;
+        ValueDeserializer(raw.value.clone())
+            .deserialize_unit_struct(name, visitor)
+            .map_err(|e| e.with_value(&raw.value, &raw.origin))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if name == secret::NAME {
+            return visitor.visit_newtype_struct(self).map_err(Error::redacted);
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Items come from indexed children like `hosts__0`, or else from the split values.
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_placeholder() {
+            return Placeholder.deserialize_seq(visitor);
+        }
+        let mut items = Vec::new();
+        if self.node.children.is_empty() {
+            for (i, (item, origin)) in self.split_values().into_iter().enumerate() {
+                items.push((i, self.child_value(&i.to_string(), item, origin)));
+            }
+        } else {
+            for (k, node) in std::mem::take(&mut self.node.children) {
+                match k.parse::<usize>() {
+                    Ok(i) => items.push((i, self.child(&k, node))),
+                    Err(_) => return Err(de::Error::invalid_type(Unexpected::Map, &visitor)),
+                }
+            }
+            items.sort_by_key(|(i, _)| *i);
+        }
+        let items = items.into_iter().map(|(i, de)| (i.to_string(), de));
+        visitor.visit_seq(NodeAccess::new(items.collect()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    /// Entries come from `key=value` pairs in the split values, overridden by the children.
+    fn deserialize_map<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_placeholder() {
+            return Placeholder.deserialize_map(visitor);
+        }
+        let children = std::mem::take(&mut self.node.children);
+        let mut entries = Vec::new();
+        for (pair, origin) in self.split_values() {
+            let (k, v) = match pair.split_once(self.options.pair_delimiter) {
+                Some((k, v)) => (k.to_string(), v.to_string()),
+                None => (pair, String::new()),
+            };
+            if !children.contains_key(&k) {
+                let de = self.child_value(&k, v, origin);
+                entries.push((k, de));
+            }
+        }
+        for (k, node) in children {
+            let de = self.child(&k, node);
+            entries.push((k, de));
+        }
+        visitor.visit_map(NodeAccess::new(entries))
+    }
+
+    /// Keys that only differ in case from a field name populate that field.
+    fn deserialize_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.is_placeholder() {
+            return Placeholder.deserialize_struct(name, fields, visitor);
+        }
+        let mut entries = Vec::new();
+        for (k, node) in std::mem::take(&mut self.node.children) {
+            let k = match fields.iter().find(|f| k.eq_ignore_ascii_case(f)) {
+                Some(f) if !fields.contains(&k.as_str()) => f.to_string(),
+                _ => k,
+            };
+            let de = self.child(&k, node);
+            if !fields.contains(&k.as_str()) && !de.is_omitted() {
+                if let Some(e) = de.unknown(&k, fields) {
+                    return Err(e);
+                }
+            }
+            entries.push((k, de));
+        }
+        // fields that already failed as missing are given a placeholder
+        for f in fields {
+            if !entries.iter().any(|(k, _)| k == f) {
+                let de = self.child(f, Node::default());
+                if de.is_placeholder() {
+                    entries.push((f.to_string(), de));
+                }
+            }
+        }
+        visitor.visit_map(NodeAccess::new(entries))
+    }
+
+    /// The variant is given by the only child, like `command.serve.port` for `Serve { port }`, or
+    /// else by the value, like `command=serve`.
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.is_placeholder() {
+            return Placeholder.deserialize_enum(name, variants, visitor);
+        }
+        let children = std::mem::take(&mut self.node.children);
+        if children.len() > 1 {
+            let given: Vec<_> = children.keys().map(String::as_str).collect();
+            return Err(de::Error::custom(format_args!(
+                "expected a single variant, got {}",
+                given.join(", ")
+            )));
+        }
+        let (variant, raw, node) = match children.into_iter().next() {
+            Some((k, node)) => (k, None, node),
+            None => match self.node.values.pop() {
+                Some(raw) => (raw.value.clone(), Some(raw), Node::default()),
+                None => return Err(de::Error::invalid_type(Unexpected::Map, &visitor)),
+            },
+        };
+        let variant = match variants.iter().find(|v| variant.eq_ignore_ascii_case(v)) {
+            Some(v) => v.to_string(),
+            None => variant,
+        };
+        let de = self.child(&variant, node);
+        visitor.visit_enum(NodeVariant { variant, raw, de })
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// A variant with its data, see [`NodeDeserializer::deserialize_enum`].
+struct NodeVariant<'a> {
+    variant: String,
+    /// The value naming the variant, if it was given as one.
+    raw: Option<Raw>,
+    de: NodeDeserializer<'a>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for NodeVariant<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self), Error> {
+        let variant = seed
+            .deserialize(self.variant.clone().into_deserializer())
+            .map_err(|e: Error| match &self.raw {
+                Some(raw) => e.with_value(&raw.value, &raw.origin),
+                None => e.prefixed(&self.variant),
+            })?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for NodeVariant<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(self.de)
+            .map_err(|e| e.prefixed(&self.variant))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.de
+            .deserialize_tuple(len, visitor)
+            .map_err(|e| e.prefixed(&self.variant))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.de
+            .deserialize_struct("", fields, visitor)
+            .map_err(|e| e.prefixed(&self.variant))
+    }
+}
+
+/// Hands out the entries of a map or the items of a sequence, putting their key in front of errors.
+struct NodeAccess<'a> {
+    entries: std::vec::IntoIter<(String, NodeDeserializer<'a>)>,
+    value: Option<(String, NodeDeserializer<'a>)>,
+}
+
+impl<'a> NodeAccess<'a> {
+    /// Leaves out the values omitted after they failed, see [`deserialize`].
+    fn new(mut entries: Vec<(String, NodeDeserializer<'a>)>) -> Self {
+        entries.retain(|(_, de)| !de.is_omitted());
+        NodeAccess {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for NodeAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((k, de)) = self.entries.next() else {
+            return Ok(None);
+        };
+        let key = seed
+            .deserialize(k.clone().into_deserializer())
+            .map_err(|e: Error| e.prefixed(&k))?;
+        self.value = Some((k, de));
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Error> {
+        let (k, de) = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(de).map_err(|e| e.prefixed(&k))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+impl<'de, 'a> SeqAccess<'de> for NodeAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Error> {
+        match self.entries.next() {
+            Some((k, de)) => seed.deserialize(de).map(Some).map_err(|e| e.prefixed(&k)),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Deserializes a single raw string as whatever type the visitor asks for.
+struct ValueDeserializer(String);
+
+/// Accepts `true`/`false`, `1`/`0`, `yes`/`no` and `on`/`off`, ignoring case.
+pub(crate) fn parse_bool(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn expected(exp: &dyn de::Expected) -> Error {
+    de::Error::custom(format_args!("expected {exp}"))
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(expected(&visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    /// Without any hint about the target type, the value stays a string.
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match parse_bool(&self.0) {
+            Some(b) => visitor.visit_bool(b),
+            None => Err(expected(&visitor)),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    /// Only unit variants can be given as a plain string.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf option newtype_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Stands in for a value that already failed, so deserialization can carry on to find further errors.
+pub(crate) struct Placeholder;
+
+impl<'de> IntoDeserializer<'de, Error> for Placeholder {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_placeholder {
+    ($($method:ident => $visit:ident($value:expr),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit($value)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Placeholder {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    deserialize_placeholder! {
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_i128 => visit_i128(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_u128 => visit_u128(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char('\0'),
+        deserialize_str => visit_str(""),
+        deserialize_string => visit_str(""),
+        deserialize_identifier => visit_str(""),
+        deserialize_bytes => visit_bytes(&[]),
+        deserialize_byte_buf => visit_bytes(&[]),
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_none()
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_tuple(0, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(SeqDeserializer::new((0..len).map(|_| Placeholder)))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(MapDeserializer::new(
+            std::iter::empty::<(&str, Placeholder)>(),
+        ))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let entries = fields.iter().map(|f| (*f, Placeholder));
+        visitor.visit_map(MapDeserializer::new(entries))
+    }
+
+    /// Picks the first variant.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(PlaceholderVariant(variants.first().copied().unwrap_or("")))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+pub(crate) struct PlaceholderVariant(pub &'static str);
+
+impl<'de> EnumAccess<'de> for PlaceholderVariant {
+    type Error = Error;
+    type Variant = Placeholder;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Placeholder), Error> {
+        let variant = seed.deserialize(self.0.into_deserializer())?;
+        Ok((variant, Placeholder))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Placeholder {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(Placeholder)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_struct("", fields, visitor)
+    }
+}
+
+/// The field most similar to `key`, if it is off by at most a third of its characters.
+fn closest<'f>(key: &str, fields: &[&'f str]) -> Option<&'f str> {
+    let key = key.to_lowercase();
+    fields
+        .iter()
+        .map(|f| (edit_distance(&key, &f.to_lowercase()), *f))
+        .filter(|(distance, _)| *distance <= key.chars().count().max(3) / 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, f)| f)
+}
+
+/// The Levenshtein distance, the number of characters to insert, delete or replace to turn `a`
+/// into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    /// The node of `kv` as given by environment variables named after the keys.
+    fn node(kv: &[(&str, &str)]) -> Node {
+        let mut node = Node::default();
+        let kv = kv.iter().map(|(k, v)| {
+            let origin = Origin::Env {
+                var: k.to_uppercase(),
+            };
+            let raw = Raw {
+                value: v.to_string(),
+                origin,
+            };
+            (k.to_string(), raw)
+        });
+        node.extend(kv, "__");
+        node
+    }
+
+    fn from<T: DeserializeOwned>(kv: &[(&str, &str)]) -> Result<T, Error> {
+        deserialize(&node(kv), &Options::default())
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+        debug: bool,
+    }
+
+    #[test]
+    fn parses_values_by_target_type() {
+        let server: Server = from(&[("host", "1"), ("port", "1"), ("debug", "YES")]).unwrap();
+        assert_eq!(
+            server,
+            Server {
+                host: "1".to_string(),
+                port: 1,
+                debug: true,
+            }
+        );
+    }
+
+    #[test]
+    fn accepts_bool_spellings() {
+        for (s, b) in [
+            ("true", true),
+            ("On", true),
+            ("1", true),
+            ("no", false),
+            ("OFF", false),
+        ] {
+            assert_eq!(parse_bool(s), Some(b), "{s}");
+        }
+        assert_eq!(parse_bool("y"), None);
+    }
+
+    #[test]
+    fn matches_field_names_ignoring_case() {
+        let server: Server = from(&[("HOST", "h"), ("Port", "1"), ("debug", "0")]).unwrap();
+        assert_eq!(server.host, "h");
+        assert_eq!(server.port, 1);
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Optional {
+        port: Option<u16>,
+        name: Option<String>,
+    }
+
+    #[test]
+    fn empty_values_are_none() {
+        let optional: Optional = from(&[("port", ""), ("name", "")]).unwrap();
+        assert_eq!(
+            optional,
+            Optional {
+                port: None,
+                name: None
+            }
+        );
+        let optional: Optional = from(&[("port", "1")]).unwrap();
+        assert_eq!(optional.port, Some(1));
+        assert_eq!(optional.name, None);
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Command {
+        Check,
+        Serve { port: u16 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Cli {
+        command: Command,
+    }
+
+    #[test]
+    fn unit_variants_are_given_by_value() {
+        let cli: Cli = from(&[("command", "check")]).unwrap();
+        assert_eq!(cli.command, Command::Check);
+        let cli: Cli = from(&[("command", "CHECK")]).unwrap();
+        assert_eq!(cli.command, Command::Check);
+    }
+
+    #[test]
+    fn struct_variants_are_given_by_child() {
+        let cli: Cli = from(&[("command__serve__port", "80")]).unwrap();
+        assert_eq!(cli.command, Command::Serve { port: 80 });
+    }
+
+    #[test]
+    fn only_one_variant_may_be_given() {
+        let err = from::<Cli>(&[("command__serve__port", "80"), ("command__check", "")]);
+        let err = err.unwrap_err();
+        assert_eq!(err.key(), Some("command"));
+        assert!(
+            err.to_string().contains("expected a single variant"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn reports_all_failed_fields() {
+        let err = from::<Server>(&[("port", "x"), ("debug", "maybe")]).unwrap_err();
+        let Error::Multiple(errors) = err else {
+            panic!("expected several errors, got {err:?}");
+        };
+        let key = |key| errors.iter().find(|e| e.key() == Some(key)).unwrap();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(key("host"), Error::Missing { .. }));
+        assert!(matches!(key("debug"), Error::Invalid { .. }));
+        match key("port") {
+            Error::Invalid { value, origin, .. } => {
+                assert_eq!(value, "x");
+                assert_eq!(
+                    origin,
+                    &Origin::Env {
+                        var: "PORT".to_string()
+                    }
+                );
+            }
+            e => panic!("expected an invalid value, got {e:?}"),
+        }
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Lists {
+        hosts: Vec<String>,
+        #[serde(default)]
+        ports: Vec<u16>,
+        #[serde(default)]
+        labels: BTreeMap<String, String>,
+    }
+
+    #[test]
+    fn splits_lists_at_the_delimiter() {
+        let lists: Lists = from(&[("hosts", "a,b,c"), ("ports", "1,2")]).unwrap();
+        assert_eq!(lists.hosts, ["a", "b", "c"]);
+        assert_eq!(lists.ports, [1, 2]);
+    }
+
+    #[test]
+    fn empty_lists_have_no_items() {
+        let lists: Lists = from(&[("hosts", "")]).unwrap();
+        assert!(lists.hosts.is_empty());
+    }
+
+    #[test]
+    fn collects_repeated_values() {
+        let lists: Lists = from(&[("hosts", "a"), ("hosts", "b,c")]).unwrap();
+        assert_eq!(lists.hosts, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn orders_indexed_items_by_index() {
+        let lists: Lists =
+            from(&[("hosts__10", "c"), ("hosts__2", "b"), ("hosts__0", "a")]).unwrap();
+        assert_eq!(lists.hosts, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn rejects_named_children_of_lists() {
+        let err = from::<Lists>(&[("hosts__first", "a")]).unwrap_err();
+        assert_eq!(err.key(), Some("hosts"));
+    }
+
+    #[test]
+    fn names_the_failed_item() {
+        let err = from::<Lists>(&[("hosts", "a"), ("ports", "1,x")]).unwrap_err();
+        assert_eq!(err.key(), Some("ports.1"));
+    }
+
+    #[test]
+    fn splits_maps_into_pairs() {
+        let lists: Lists = from(&[("hosts", ""), ("labels", "a=1,b=x=y,c")]).unwrap();
+        let labels: Vec<_> = lists
+            .labels
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(labels, [("a", "1"), ("b", "x=y"), ("c", "")]);
+    }
+
+    #[test]
+    fn children_override_map_pairs() {
+        let kv = [("hosts", ""), ("labels", "a=1,b=2"), ("labels__b", "3")];
+        let lists: Lists = from(&kv).unwrap();
+        assert_eq!(lists.labels["a"], "1");
+        assert_eq!(lists.labels["b"], "3");
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Nested {
+        name: String,
+        database: Database,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Database {
+        host: String,
+        port: u16,
+    }
+
+    #[test]
+    fn prefixes_keys_of_nested_fields() {
+        let err = from::<Nested>(&[("name", "app"), ("database__port", "x")]).unwrap_err();
+        let Error::Multiple(errors) = err else {
+            panic!("expected several errors, got {err:?}");
+        };
+        let mut keys: Vec<_> = errors.iter().filter_map(Error::key).collect();
+        keys.sort();
+        assert_eq!(keys, ["database.host", "database.port"]);
+    }
+
+    #[test]
+    fn reports_a_single_error_alone() {
+        let err = from::<Nested>(&[("database__host", "h"), ("database__port", "1")]).unwrap_err();
+        assert_eq!(
+            err,
+            Error::Missing {
+                key: "name".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,528 @@
+//! Parser for `.env` files.
+//!
+//! Supports:
+//! - comments on their own line and after values, separated by whitespace: `PORT=80 # http`
+//! - an optional `export` in front of keys
+//! - values containing `=`: `DATABASE_URL=postgres://u:p@h/db?sslmode=require`
+//! - single-quoted values, without escape sequences
+//! - double-quoted values with the escape sequences `\n`, `\r`, `\t`, `\"`, `\\` and `\$`
+//! - quoted values spanning multiple lines
+//! - empty values like `PORT=` or `PORT=""`, which are skipped
+//! - references to other variables: `${VAR}`, `$VAR` and `${VAR:-default}`, see [`expand`]
+
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::{node::Node, Error, Options, Origin, Source};
+
+/// A `KEY=VALUE` pair in a `.env` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub key: String,
+    pub value: Template,
+    /// Line of the key, counting from 1.
+    pub line: usize,
+}
+
+/// A value that may contain references to other variables.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Template(Vec<Part>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Var {
+        name: String,
+        /// Used if the variable is not set, or, with `:-`, also if it is empty.
+        default: Option<(Template, bool)>,
+    },
+}
+
+/// A syntax error, with the line it occurred in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SyntaxError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// The file at `path`, if it exists. With `search_parents`, a relative `path` is also looked for
+/// in the parent directories of the current one, up to the root of the git repository.
+pub(crate) fn find(path: &Path, search_parents: bool) -> Option<PathBuf> {
+    if !search_parents || path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let cwd = env::current_dir().ok()?;
+    for dir in cwd.ancestors() {
+        let candidate = dir.join(path);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// If `expand_single_quoted` is false, `$` in single-quoted values is taken literally.
+pub(crate) fn parse(src: &str, expand_single_quoted: bool) -> Result<Vec<Entry>, SyntaxError> {
+    let lines: Vec<&str> = src.lines().collect();
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = i + 1;
+        let error = |message: &str| SyntaxError {
+            line,
+            message: message.to_string(),
+        };
+        let trimmed = lines[i].trim();
+        i += 1;
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let trimmed = trimmed
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map_or(trimmed, str::trim_start);
+        let Some((key, rest)) = trimmed.split_once('=') else {
+            return Err(error("expected `KEY=VALUE`"));
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(error("invalid key"));
+        }
+        let value = match rest.trim_start().chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let mut quoted = rest.trim_start()[1..].to_string();
+                let value = loop {
+                    if let Some((value, after)) = closing_quote(&quoted, quote) {
+                        let after = after.trim_start();
+                        if !after.is_empty() && !after.starts_with('#') {
+                            return Err(error("unexpected characters after closing quote"));
+                        }
+                        break value;
+                    }
+                    let Some(next) = lines.get(i) else {
+                        return Err(error("unterminated quoted value"));
+                    };
+                    quoted.push('\n');
+                    quoted.push_str(next);
+                    i += 1;
+                };
+                if quote == '"' {
+                    Template::parse(value, true, true)
+                } else {
+                    Template::parse(value, false, expand_single_quoted)
+                }
+            }
+            _ => Template::parse(strip_inline_comment(rest).trim(), false, true),
+        };
+        let value = value.map_err(|e| error(&e))?;
+        // like a key that is not there, so defaults apply
+        if value.0.is_empty() {
+            continue;
+        }
+        entries.push(Entry {
+            key: key.to_string(),
+            value,
+            line,
+        });
+    }
+    Ok(entries)
+}
+
+/// Splits `s` at the first `quote` not escaped by a backslash, into the still escaped value and
+/// the rest after the quote.
+fn closing_quote(s: &str, quote: char) -> Option<(&str, &str)> {
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some((&s[..i], &s[i + 1..])),
+            '\\' if quote == '"' => {
+                chars.next()?;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A `#` only starts a comment after whitespace, so `COLOR=#fff` keeps its value.
+fn strip_inline_comment(value: &str) -> &str {
+    let mut prev_whitespace = false;
+    for (i, c) in value.char_indices() {
+        if c == '#' && prev_whitespace {
+            return &value[..i];
+        }
+        prev_whitespace = c.is_whitespace();
+    }
+    value
+}
+
+impl Template {
+    /// Resolves escape sequences if `escapes` is set and variable references if `refs` is set.
+    fn parse(s: &str, escapes: bool, refs: bool) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if escapes => match chars.next() {
+                    Some('n') => literal.push('\n'),
+                    Some('r') => literal.push('\r'),
+                    Some('t') => literal.push('\t'),
+                    Some(c @ ('"' | '\\' | '$')) => literal.push(c),
+                    Some(c) => {
+                        literal.push('\\');
+                        literal.push(c);
+                    }
+                    None => literal.push('\\'),
+                },
+                '$' if refs => {
+                    let var = match chars.peek() {
+                        Some('{') => {
+                            chars.next();
+                            let mut braced = String::new();
+                            let mut depth = 0;
+                            loop {
+                                let Some(c) = chars.next() else {
+                                    return Err("unterminated `${`".into());
+                                };
+                                match c {
+                                    '{' => depth += 1,
+                                    '}' if depth == 0 => break,
+                                    '}' => depth -= 1,
+                                    _ => {}
+                                }
+                                braced.push(c);
+                            }
+                            Some(Self::parse_braced(&braced, escapes)?)
+                        }
+                        Some(&c) if is_name_start(c) => {
+                            let mut name = String::new();
+                            while let Some(&c) = chars.peek().filter(|c| is_name_char(**c)) {
+                                name.push(c);
+                                chars.next();
+                            }
+                            Some(Part::Var {
+                                name,
+                                default: None,
+                            })
+                        }
+                        _ => None,
+                    };
+                    match var {
+                        Some(var) => {
+                            if !literal.is_empty() {
+                                parts.push(Part::Literal(std::mem::take(&mut literal)));
+                            }
+                            parts.push(var);
+                        }
+                        None => literal.push('$'),
+                    }
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template(parts))
+    }
+
+    /// Parses the inside of `${...}`: `VAR`, `VAR-default` or `VAR:-default`.
+    fn parse_braced(braced: &str, escapes: bool) -> Result<Part, String> {
+        let name_len = braced.find(|c| !is_name_char(c)).unwrap_or(braced.len());
+        let (name, rest) = braced.split_at(name_len);
+        if !name.starts_with(is_name_start) {
+            return Err(format!("invalid variable name in `${{{braced}}}`"));
+        }
+        let default = if rest.is_empty() {
+            None
+        } else if let Some(default) = rest.strip_prefix(":-") {
+            Some((Template::parse(default, escapes, true)?, true))
+        } else if let Some(default) = rest.strip_prefix('-') {
+            Some((Template::parse(default, escapes, true)?, false))
+        } else {
+            return Err(format!("invalid variable reference `${{{braced}}}`"));
+        };
+        Ok(Part::Var {
+            name: name.to_string(),
+            default,
+        })
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Replaces the values from `.env` files in `node` with their expanded templates.
+///
+/// A reference `${VAR}` in a `.env` file resolves to
+/// 1. the last `VAR` above it in the same file,
+/// 2. else the environment variable `VAR`, if [`Source::Env`] is read,
+/// 3. else the value of `VAR` in the merged sources, with nested keys split at
+///    [`Options::separator`],
+/// 4. else the default, or an empty string.
+///
+/// Values found this way are expanded in turn; references in a cycle give an error.
+pub(crate) fn expand(
+    node: &mut Node,
+    files: &BTreeMap<PathBuf, Vec<Entry>>,
+    options: &Options,
+) -> Result<(), Error> {
+    let merged = node.clone();
+    let mut expander = Expander {
+        files,
+        merged: &merged,
+        options,
+        stack: Vec::new(),
+    };
+    node.try_for_each_value(&mut |raw| {
+        if let Origin::Dotenv { path, line } = &raw.origin {
+            if let Some((path, entries)) = files.get_key_value(path.as_path()) {
+                if let Some(entry) = entries.iter().find(|e| e.line == *line) {
+                    raw.value = expander.expand_entry(path, entry)?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+struct Expander<'a> {
+    files: &'a BTreeMap<PathBuf, Vec<Entry>>,
+    merged: &'a Node,
+    options: &'a Options,
+    /// Entries currently being expanded, to detect cycles.
+    stack: Vec<(&'a Path, &'a Entry)>,
+}
+
+impl<'a> Expander<'a> {
+    fn expand_entry(&mut self, path: &'a Path, entry: &'a Entry) -> Result<String, Error> {
+        if let Some(i) = self
+            .stack
+            .iter()
+            .position(|(p, e)| *p == path && e.line == entry.line)
+        {
+            let (path, first) = self.stack[i];
+            let mut cycle: Vec<&str> = self.stack[i..]
+                .iter()
+                .map(|(_, e)| e.key.as_str())
+                .collect();
+            cycle.push(&entry.key);
+            return Err(Error::Syntax {
+                origin: Origin::Dotenv {
+                    path: path.to_path_buf(),
+                    line: first.line,
+                },
+                message: format!("cyclic variable reference {}", cycle.join(" -> ")),
+            });
+        }
+        self.stack.push((path, entry));
+        let value = self.expand_template(path, entry.line, &entry.value);
+        self.stack.pop();
+        value
+    }
+
+    fn expand_template(
+        &mut self,
+        path: &'a Path,
+        line: usize,
+        template: &'a Template,
+    ) -> Result<String, Error> {
+        let mut value = String::new();
+        for part in &template.0 {
+            match part {
+                Part::Literal(s) => value.push_str(s),
+                Part::Var { name, default } => {
+                    let resolved = self.lookup(path, line, name)?;
+                    match (resolved, default) {
+                        (Some(v), Some((_, true))) if v.is_empty() => {}
+                        (Some(v), _) => {
+                            value.push_str(&v);
+                            continue;
+                        }
+                        (None, _) => {}
+                    }
+                    if let Some((default, _)) = default {
+                        value.push_str(&self.expand_template(path, line, default)?);
+                    }
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Resolves `name` referenced in `line` of the file at `from`.
+    fn lookup(&mut self, from: &'a Path, line: usize, name: &str) -> Result<Option<String>, Error> {
+        let earlier = self.files.get(from).and_then(|entries| {
+            entries
+                .iter()
+                .rev()
+                .find(|e| e.line < line && e.key == name)
+        });
+        if let Some(entry) = earlier {
+            return self.expand_entry(from, entry).map(Some);
+        }
+        if self.options.sources.contains(&Source::Env) {
+            if let Some(value) = crate::env_var(self.options, name) {
+                return Ok(Some(value));
+            }
+        }
+        let separator = self.options.separator.as_str();
+        let segments: Vec<&str> = if separator.is_empty() {
+            vec![name]
+        } else {
+            name.split(separator).collect()
+        };
+        let Some(raw) = self.merged.get(segments).and_then(Node::value) else {
+            return Ok(None);
+        };
+        let Origin::Dotenv { path, line: l } = &raw.origin else {
+            return Ok(Some(raw.value.clone()));
+        };
+        if path == from && *l == line {
+            // a variable referencing itself, like `PATH=$PATH:/bin`, is not set otherwise
+            return Ok(None);
+        }
+        match self.files.get_key_value(path.as_path()) {
+            Some((path, entries)) => match entries.iter().find(|e| e.line == *l) {
+                Some(entry) => self.expand_entry(path, entry).map(Some),
+                None => Ok(Some(raw.value.clone())),
+            },
+            None => Ok(Some(raw.value.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The keys and values of `src`, which must not contain variable references.
+    fn values(src: &str) -> Vec<(String, String)> {
+        let entries = parse(src, false).unwrap();
+        entries
+            .into_iter()
+            .map(|entry| {
+                let value = entry.value.0.iter().map(|part| match part {
+                    Part::Literal(s) => s.as_str(),
+                    Part::Var { name, .. } => panic!("unexpected reference to `{name}`"),
+                });
+                (entry.key, value.collect())
+            })
+            .collect()
+    }
+
+    fn value(src: &str) -> String {
+        values(src).remove(0).1
+    }
+
+    #[test]
+    fn keeps_equals_signs_in_values() {
+        assert_eq!(
+            value("DATABASE_URL=postgres://u:p@h/db?sslmode=require"),
+            "postgres://u:p@h/db?sslmode=require"
+        );
+        assert_eq!(value("A=b=c"), "b=c");
+    }
+
+    #[test]
+    fn skips_export() {
+        assert_eq!(values("export PORT=80"), [("PORT".into(), "80".into())]);
+        assert_eq!(values("exported=1"), [("exported".into(), "1".into())]);
+    }
+
+    #[test]
+    fn resolves_escapes_only_in_double_quotes() {
+        assert_eq!(value(r#"A='a\nb'"#), r"a\nb");
+        assert_eq!(value(r#"A="a\nb""#), "a\nb");
+        assert_eq!(value(r#"A="say \"hi\"""#), r#"say "hi""#);
+        assert_eq!(value(r#"A="\$HOME""#), "$HOME");
+        assert_eq!(value(r#"A="a\\b""#), r"a\b");
+    }
+
+    #[test]
+    fn takes_dollars_in_single_quotes_literally() {
+        assert_eq!(value("A='$HOME'"), "$HOME");
+        let entries = parse("A='$HOME'", true).unwrap();
+        assert!(matches!(&entries[0].value.0[..], [Part::Var { name, .. }] if name == "HOME"));
+    }
+
+    #[test]
+    fn parses_references() {
+        let entries = parse("A=${HOME:-/root}/x", false).unwrap();
+        let [Part::Var { name, default }, Part::Literal(rest)] = &entries[0].value.0[..] else {
+            panic!("unexpected {:?}", entries[0].value);
+        };
+        assert_eq!(name, "HOME");
+        assert_eq!(
+            default,
+            &Some((Template(vec![Part::Literal("/root".into())]), true))
+        );
+        assert_eq!(rest, "/x");
+    }
+
+    #[test]
+    fn joins_multi_line_values() {
+        let src = "KEY=\"-----BEGIN-----\nabc\n-----END-----\"\nNEXT='x\ny'\nLAST=z";
+        let entries = parse(src, false).unwrap();
+        let lines: Vec<_> = entries.iter().map(|e| (e.key.as_str(), e.line)).collect();
+        assert_eq!(lines, [("KEY", 1), ("NEXT", 4), ("LAST", 6)]);
+        assert_eq!(
+            values(src),
+            [
+                ("KEY".into(), "-----BEGIN-----\nabc\n-----END-----".into()),
+                ("NEXT".into(), "x\ny".into()),
+                ("LAST".into(), "z".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_empty_values() {
+        let src = "PORT=\nA=''\nB=\"\" # none\nC=1";
+        assert_eq!(values(src), [("C".into(), "1".into())]);
+        assert_eq!(parse("A=${EMPTY}", false).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn strips_comments_after_whitespace() {
+        assert_eq!(value("COLOR=#fff"), "#fff");
+        assert_eq!(value("A=b # c"), "b");
+        assert_eq!(value("A=b#c"), "b#c");
+        assert_eq!(value("A=\"b # c\" # d"), "b # c");
+        assert_eq!(
+            values("# only a comment\n\nA=b"),
+            [("A".into(), "b".into())]
+        );
+    }
+
+    #[test]
+    fn reports_the_line_of_syntax_errors() {
+        let error = |src: &str| parse(src, false).unwrap_err();
+        assert_eq!(
+            error("A=1\n\nnot a pair"),
+            SyntaxError {
+                line: 3,
+                message: "expected `KEY=VALUE`".into()
+            }
+        );
+        assert_eq!(error("A=1\nB C=2").line, 2);
+        assert_eq!(error("A=1\nB=\"open\nstill open").line, 2);
+        assert_eq!(
+            error("A=\"x\" y").message,
+            "unexpected characters after closing quote"
+        );
+        assert_eq!(error("A=1\nB=${X").message, "unterminated `${`");
+    }
+}
@@ -0,0 +1,386 @@
+use std::{fmt, path::PathBuf};
+
+use serde::de;
+
+use crate::Field;
+
+/// Where a raw value was given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Origin {
+    /// A line in a `.env` file, counting from 1.
+    Dotenv { path: PathBuf, line: usize },
+    /// A TOML, YAML or JSON config file.
+    File { path: PathBuf },
+    /// A file named by a key like `DB_PASSWORD_FILE`, which was given at `from`.
+    KeyFile { path: PathBuf, from: Box<Origin> },
+    /// An environment variable, with its full name.
+    Env { var: String },
+    /// A CLI argument, with the position of the argument holding the value (the program name being 0)
+    /// and the flag as it was written.
+    Arg { index: usize, flag: String },
+    /// A CLI argument without flag, with its position.
+    Positional { index: usize },
+    /// The default given with `#[from_env(default = "...")]`.
+    Default,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Dotenv { path, line } => write!(f, "{} line {line}", path.display()),
+            Origin::File { path } => write!(f, "{}", path.display()),
+            Origin::KeyFile { path, from } => write!(f, "{} from {from}", path.display()),
+            Origin::Env { var } => write!(f, "env var {var}"),
+            Origin::Arg { flag, .. } => write!(f, "CLI arg {flag}"),
+            Origin::Positional { index } => write!(f, "CLI arg {index}"),
+            Origin::Default => f.write_str("default"),
+        }
+    }
+}
+
+/// Error returned by [`FromEnv::from_env`](crate::FromEnv::from_env).
+///
+/// Keys are the paths of fields, joined by `.`, like `database.port`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// No value was given for a field without default.
+    Missing { key: String },
+    /// The given value could not be parsed into the type of the field.
+    Invalid {
+        key: String,
+        value: String,
+        origin: Origin,
+        message: String,
+    },
+    /// A source like a `.env` file could not be parsed, e.g. because of an unterminated quote
+    /// or cyclic variable references.
+    Syntax { origin: Origin, message: String },
+    /// A key matched no field, only raised in [strict mode](crate::Options::strict). `suggestion`
+    /// is the closest field name, if there is one close enough.
+    Unknown {
+        key: String,
+        origin: Origin,
+        suggestion: Option<String>,
+    },
+    /// Two fields were given that conflict, see [`Relation::ConflictsWith`](crate::Relation).
+    Conflict {
+        key: String,
+        origin: Origin,
+        other: String,
+        other_origin: Box<Origin>,
+    },
+    /// No value was given for a field that another one requires, see
+    /// [`Relation::Requires`](crate::Relation) and [`Relation::RequiredIf`](crate::Relation).
+    /// `value` is the value of `by` that makes the field required, if not any value does.
+    Required {
+        key: String,
+        by: String,
+        value: Option<String>,
+        origin: Origin,
+    },
+    /// Any other error, `key` being empty if it does not concern a specific field.
+    Custom { key: String, message: String },
+    /// Several fields were missing or invalid.
+    Multiple(Vec<Error>),
+}
+
+impl Error {
+    /// A single error, or [`Error::Multiple`] if there is more than one.
+    pub(crate) fn from_many(mut errors: Vec<Error>) -> Error {
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            Error::Multiple(errors)
+        }
+    }
+
+    /// The key of the field this error concerns, if any.
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            Error::Missing { key }
+            | Error::Invalid { key, .. }
+            | Error::Unknown { key, .. }
+            | Error::Conflict { key, .. }
+            | Error::Required { key, .. }
+            | Error::Custom { key, .. } => Some(key).filter(|k| !k.is_empty()).map(String::as_str),
+            Error::Syntax { .. } | Error::Multiple(_) => None,
+        }
+    }
+
+    /// Puts `segment` in front of the key, for errors bubbling up from a nested value.
+    pub(crate) fn prefixed(self, segment: &str) -> Self {
+        let join = |key: String| {
+            if key.is_empty() {
+                segment.to_string()
+            } else {
+                format!("{segment}.{key}")
+            }
+        };
+        match self {
+            Error::Missing { key } => Error::Missing { key: join(key) },
+            Error::Invalid {
+                key,
+                value,
+                origin,
+                message,
+            } => Error::Invalid {
+                key: join(key),
+                value,
+                origin,
+                message,
+            },
+            Error::Unknown {
+                key,
+                origin,
+                suggestion,
+            } => Error::Unknown {
+                key: join(key),
+                origin,
+                suggestion,
+            },
+            Error::Conflict {
+                key,
+                origin,
+                other,
+                other_origin,
+            } => Error::Conflict {
+                key: join(key),
+                origin,
+                other: join(other),
+                other_origin,
+            },
+            Error::Required {
+                key,
+                by,
+                value,
+                origin,
+            } => Error::Required {
+                key: join(key),
+                by: join(by),
+                value,
+                origin,
+            },
+            Error::Custom { key, message } => Error::Custom {
+                key: join(key),
+                message,
+            },
+            Error::Multiple(errors) => {
+                Error::Multiple(errors.into_iter().map(|e| e.prefixed(segment)).collect())
+            }
+            e @ Error::Syntax { .. } => e,
+        }
+    }
+
+    /// Leaves out the raw value, for errors about a [`Secret`](crate::Secret).
+    pub(crate) fn redacted(self) -> Self {
+        match self {
+            Error::Invalid {
+                key,
+                origin,
+                message,
+                ..
+            } => Error::Invalid {
+                key,
+                value: "***".to_string(),
+                origin,
+                message,
+            },
+            Error::Multiple(errors) => {
+                Error::Multiple(errors.into_iter().map(Error::redacted).collect())
+            }
+            e => e,
+        }
+    }
+
+    /// Leaves out the raw values of fields marked with `#[from_env(secret)]`.
+    pub(crate) fn redacted_secrets(self, fields: &[Field]) -> Self {
+        match self {
+            Error::Multiple(errors) => Error::Multiple(
+                errors
+                    .into_iter()
+                    .map(|e| e.redacted_secrets(fields))
+                    .collect(),
+            ),
+            e => {
+                let secret = e.key().is_some_and(|key| {
+                    let name = key.split('.').next().unwrap_or(key);
+                    fields
+                        .iter()
+                        .any(|f| f.secret && f.name.eq_ignore_ascii_case(name))
+                });
+                if secret {
+                    e.redacted()
+                } else {
+                    e
+                }
+            }
+        }
+    }
+
+    /// Attaches the raw value to an error raised while deserializing it.
+    pub(crate) fn with_value(self, value: &str, origin: &Origin) -> Self {
+        match self {
+            Error::Custom { key, message } if key.is_empty() => Error::Invalid {
+                key,
+                value: value.to_string(),
+                origin: origin.clone(),
+                message,
+            },
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Missing { key } => write!(f, "missing value for `{key}`"),
+            Error::Invalid {
+                key,
+                value,
+                origin,
+                message,
+            } => write!(
+                f,
+                "invalid value {value:?} for `{key}` ({origin}): {message}"
+            ),
+            Error::Unknown {
+                key,
+                origin,
+                suggestion,
+            } => {
+                write!(f, "unknown key `{key}` ({origin})")?;
+                match suggestion {
+                    Some(suggestion) => write!(f, ", did you mean `{suggestion}`?"),
+                    None => Ok(()),
+                }
+            }
+            Error::Conflict {
+                key,
+                origin,
+                other,
+                other_origin,
+            } => write!(
+                f,
+                "`{key}` ({origin}) conflicts with `{other}` ({other_origin})"
+            ),
+            Error::Required {
+                key,
+                by,
+                value: None,
+                origin,
+            } => write!(
+                f,
+                "missing value for `{key}`, required by `{by}` ({origin})"
+            ),
+            Error::Required {
+                key,
+                by,
+                value: Some(value),
+                origin,
+            } => write!(
+                f,
+                "missing value for `{key}`, required because `{by}` is {value:?} ({origin})"
+            ),
+            Error::Syntax { origin, message } => write!(f, "{origin}: {message}"),
+            Error::Custom { key, message } if key.is_empty() => f.write_str(message),
+            Error::Custom { key, message } => write!(f, "`{key}`: {message}"),
+            Error::Multiple(errors) => {
+                write!(f, "{} errors:", errors.len())?;
+                for e in errors {
+                    write!(f, "\n  {e}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom {
+            key: String::new(),
+            message: msg.to_string(),
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::Missing {
+            key: field.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missing(key: &str) -> Error {
+        Error::Missing {
+            key: key.to_string(),
+        }
+    }
+
+    #[test]
+    fn a_single_error_is_not_wrapped() {
+        assert_eq!(Error::from_many(vec![missing("a")]), missing("a"));
+        assert_eq!(
+            Error::from_many(vec![missing("a"), missing("b")]),
+            Error::Multiple(vec![missing("a"), missing("b")])
+        );
+    }
+
+    #[test]
+    fn prefixes_keys_of_nested_errors() {
+        assert_eq!(
+            missing("port").prefixed("database"),
+            missing("database.port")
+        );
+        let custom: Error = de::Error::custom("expected u16");
+        assert_eq!(custom.prefixed("port").key(), Some("port"));
+        let multiple = Error::Multiple(vec![missing("host"), missing("port")]);
+        assert_eq!(
+            multiple.prefixed("database"),
+            Error::Multiple(vec![missing("database.host"), missing("database.port")])
+        );
+    }
+
+    #[test]
+    fn prefixes_the_other_key_of_relations() {
+        let conflict = Error::Conflict {
+            key: "cert".to_string(),
+            origin: Origin::Default,
+            other: "insecure".to_string(),
+            other_origin: Box::new(Origin::Default),
+        };
+        match conflict.prefixed("tls") {
+            Error::Conflict { key, other, .. } => {
+                assert_eq!(key, "tls.cert");
+                assert_eq!(other, "tls.insecure");
+            }
+            e => panic!("expected a conflict, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn syntax_errors_keep_their_origin_only() {
+        let syntax = Error::Syntax {
+            origin: Origin::Default,
+            message: "unterminated quote".to_string(),
+        };
+        assert_eq!(syntax.clone().prefixed("database"), syntax);
+    }
+
+    #[test]
+    fn lists_all_errors() {
+        let multiple = Error::Multiple(vec![missing("host"), missing("port")]);
+        assert_eq!(
+            multiple.to_string(),
+            "2 errors:\n  missing value for `host`\n  missing value for `port`"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{schema::Schema, Constraint, Relation};
+
+/// How a field is named in the sources and described in `--help`, given with `#[from_env(...)]`
+/// and returned by [`FromEnv::fields`](crate::FromEnv::fields).
+///
+/// Explicit names replace the ones derived from the field name: a field `database_url` with
+/// `env = "DB_URL"` is read from `DB_URL` but not from `DATABASE_URL`.
+///
+/// Only the fields of the type itself have a `Field`. The `#[from_env(...)]` attributes of a
+/// struct used as a field of another one are ignored there: its keys are derived from the field
+/// names, and its defaults, constraints and relations are not applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    /// The name of the field as serde sees it, after `#[serde(rename)]`.
+    pub name: &'static str,
+    /// The full name of the environment variable or `.env` key, not affected by
+    /// [`Options::env_prefix`](crate::Options::env_prefix).
+    pub env: Option<&'static str>,
+    /// The CLI flag without `--`.
+    pub long: Option<&'static str>,
+    /// A short CLI flag like `-d`.
+    pub short: Option<char>,
+    /// A description, defaulting to the doc comment of the field.
+    pub help: Option<&'static str>,
+    /// A raw value used if no source gives one, parsed like any other value.
+    pub default: Option<&'static str>,
+    /// Whether the value should be kept out of output like logs.
+    pub secret: bool,
+    /// Whether the field is read from CLI args without flag, in the order of the fields. A
+    /// sequence takes all remaining ones, and is empty if there are none.
+    pub positional: bool,
+    /// Whether the field is an enum whose variant is chosen by the first CLI arg without flag,
+    /// like `serve` in `app serve --port 80`. Flags after it populate the fields of the variant.
+    pub subcommand: bool,
+    /// Checks of the value after deserializing.
+    pub constraints: &'static [Constraint],
+    /// Relations to other fields, checked across all sources.
+    pub relations: &'static [Relation],
+}
+
+impl Field {
+    /// A field without explicit names.
+    pub const fn new(name: &'static str) -> Self {
+        Field {
+            name,
+            env: None,
+            long: None,
+            short: None,
+            help: None,
+            default: None,
+            secret: false,
+            positional: false,
+            subcommand: false,
+            constraints: &[],
+            relations: &[],
+        }
+    }
+
+    /// The raw value used if no source gives one: the `default`, or no items for a positional
+    /// sequence. `schema` is the schema of the field.
+    pub(crate) fn default_value(&self, schema: &Schema) -> Option<&'static str> {
+        match self.default {
+            Some(default) => Some(default),
+            None if self.positional && schema.is_list() => Some(""),
+            None => None,
+        }
+    }
+
+    /// The field at `path` if it is a field of the type itself, as explicit attributes only exist
+    /// for those and not for the fields of nested types.
+    pub(crate) fn top_level<'a>(fields: &'a [Field], path: &[&str]) -> Option<&'a Field> {
+        match path {
+            [name] => fields.iter().find(|f| f.name == *name),
+            _ => None,
+        }
+    }
+
+    /// The field with the explicit `env` name `var`, if any.
+    pub(crate) fn by_env<'a>(fields: &'a [Field], var: &str) -> Option<&'a Field> {
+        fields
+            .iter()
+            .find(|f| f.env.is_some_and(|env| env.eq_ignore_ascii_case(var)))
+    }
+
+    /// The field with the explicit `long` flag `flag`, if any.
+    pub(crate) fn by_long<'a>(fields: &'a [Field], flag: &str) -> Option<&'a Field> {
+        fields.iter().find(|f| f.long == Some(flag))
+    }
+
+    /// The field with the `short` flag `c`, if any.
+    pub(crate) fn by_short(fields: &[Field], c: char) -> Option<&Field> {
+        fields.iter().find(|f| f.short == Some(c))
+    }
+
+    /// The explicit name given by `explicit` to the field named `key`, if any, in which case the
+    /// key itself is not read.
+    pub(crate) fn renamed(
+        fields: &[Field],
+        key: &str,
+        explicit: impl Fn(&Field) -> Option<&'static str>,
+    ) -> Option<&'static str> {
+        fields
+            .iter()
+            .filter(|f| f.name.eq_ignore_ascii_case(key))
+            .find_map(explicit)
+    }
+}
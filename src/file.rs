@@ -0,0 +1,160 @@
+//! Config files in TOML, YAML or JSON, each behind the cargo feature of the same name.
+//!
+//! Tables become nested keys and arrays indexed keys, so `[database] port = 5432` populates the
+//! same field as `DATABASE__PORT=5432` and `ports = [80, 443]` the same as `PORTS__0=80`.
+
+use std::{fmt, fs, path::Path};
+
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::{node::Node, Error, Origin, Raw};
+
+/// Reads the file at `path`, choosing the format by its extension.
+pub(crate) fn read(path: &Path) -> Result<Node, Error> {
+    let custom = |message: String| Error::Custom {
+        key: String::new(),
+        message,
+    };
+    let parse: Result<Parse, Error> = match path.extension().and_then(|ext| ext.to_str()) {
+        #[cfg(feature = "toml")]
+        Some("toml") => Ok(parse_toml),
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => Ok(parse_yaml),
+        #[cfg(feature = "json")]
+        Some("json") => Ok(parse_json),
+        Some(ext) if ["toml", "yaml", "yml", "json"].contains(&ext) => {
+            let feature = if ext == "yml" { "yaml" } else { ext };
+            Err(custom(format!(
+                "reading config file `{}` needs the `{feature}` feature of from_env",
+                path.display()
+            )))
+        }
+        _ => Err(custom(format!(
+            "unknown format of config file `{}`, expected .toml, .yaml, .yml or .json",
+            path.display()
+        ))),
+    };
+    let parse = parse?;
+    let src = fs::read_to_string(path).map_err(|e| {
+        custom(format!(
+            "could not read config file `{}`: {e}",
+            path.display()
+        ))
+    })?;
+    let origin = Origin::File {
+        path: path.to_path_buf(),
+    };
+    parse(&src, NodeSeed(&origin)).map_err(|message| Error::Syntax { origin, message })
+}
+
+type Parse = fn(&str, NodeSeed) -> Result<Node, String>;
+
+#[cfg(feature = "toml")]
+fn parse_toml(src: &str, seed: NodeSeed) -> Result<Node, String> {
+    seed.deserialize(toml::Deserializer::new(src))
+        .map_err(|e| e.message().to_string())
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(src: &str, seed: NodeSeed) -> Result<Node, String> {
+    seed.deserialize(serde_yaml::Deserializer::from_str(src))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "json")]
+fn parse_json(src: &str, seed: NodeSeed) -> Result<Node, String> {
+    seed.deserialize(&mut serde_json::Deserializer::from_str(src))
+        .map_err(|e| e.to_string())
+}
+
+/// Deserializes any self-describing format into a [`Node`], with values of the given origin.
+#[derive(Clone, Copy)]
+struct NodeSeed<'a>(&'a Origin);
+
+impl<'a> NodeSeed<'a> {
+    fn value(self, value: impl ToString) -> Node {
+        Node {
+            values: vec![Raw {
+                value: value.to_string(),
+                origin: self.0.clone(),
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for NodeSeed<'a> {
+    type Value = Node;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for NodeSeed<'a> {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a table, array or scalar")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Node, E> {
+        Ok(self.value(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Node, E> {
+        Ok(self.value(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Node, E> {
+        Ok(self.value(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Node, E> {
+        Ok(self.value(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Node, E> {
+        Ok(self.value(v))
+    }
+
+    /// `null` counts as not given.
+    fn visit_unit<E>(self) -> Result<Node, E> {
+        Ok(Node::default())
+    }
+
+    fn visit_none<E>(self) -> Result<Node, E> {
+        Ok(Node::default())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut node = Node::default();
+        while let Some(item) = seq.next_element_seed(self)? {
+            node.children.insert(node.children.len().to_string(), item);
+        }
+        if node.children.is_empty() {
+            // an empty list
+            node = self.value("");
+        }
+        Ok(node)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut node = Node::default();
+        while let Some(key) = map.next_key::<String>()? {
+            let child = map.next_value_seed(self)?;
+            // TOML dates and times are given as a table with this single key
+            if key == "$__toml_private_datetime" {
+                return Ok(child);
+            }
+            if !child.values.is_empty() || !child.children.is_empty() {
+                node.children.insert(key, child);
+            }
+        }
+        Ok(node)
+    }
+}
@@ -0,0 +1,314 @@
+//! The `--help` output and the `.env` template, listing the keys a type accepts.
+
+use std::{env, path::Path};
+
+use crate::{
+    args,
+    schema::{Schema, SchemaField},
+    Field, Options, Source,
+};
+
+/// A key a type accepts.
+struct Row {
+    flag: String,
+    ty: String,
+    default: Option<String>,
+    required: bool,
+    secret: bool,
+    env: String,
+    /// The key in `.env` files, which has no [`Options::env_prefix`].
+    dotenv: String,
+    help: String,
+}
+
+impl Row {
+    /// The default column of the usage table.
+    fn default_column(&self) -> String {
+        match &self.default {
+            Some(_) if self.secret => "***".to_string(),
+            Some(default) => default.clone(),
+            None if self.required => "required".to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+/// Rows for all keys of `schema`, in the order of the fields.
+fn rows(schema: &Schema, fields: &[Field], options: &Options) -> Vec<Row> {
+    let mut table = Table {
+        fields,
+        options,
+        rows: Vec::new(),
+    };
+    for f in schema.fields() {
+        table.collect(f, &mut Vec::new(), true, None);
+    }
+    table.rows
+}
+
+/// A table of all keys with their CLI flag, type, default, environment variable and description.
+pub(crate) fn usage(schema: &Schema, fields: &[Field], options: &Options) -> String {
+    let row = |cells: [&str; 5]| cells.map(str::to_string);
+    let mut rows = vec![row(["OPTION", "TYPE", "DEFAULT", "ENV", "DESCRIPTION"])];
+    for r in self::rows(schema, fields, options) {
+        let default = r.default_column();
+        rows.push([r.flag, r.ty, default, r.env, r.help]);
+    }
+    if cfg!(any(feature = "toml", feature = "yaml", feature = "json"))
+        && options.sources.contains(&Source::File)
+    {
+        let flag = format!("    --{}", args::CONFIG_FLAG);
+        let default = options
+            .config_path
+            .as_ref()
+            .map_or(String::new(), |path| path.display().to_string());
+        let help = "Read this TOML, YAML or JSON config file";
+        rows.push(row([&flag, "path", &default, "", help]));
+    }
+    if options.dotenv_override && options.sources.contains(&Source::Dotenv) {
+        let flag = format!("    --{}", args::ENV_FILE_FLAG);
+        let default = options.dotenv_path.display().to_string();
+        let help = "Read this .env file instead";
+        rows.push(row([&flag, "path", &default, "DOTENV_PATH", help]));
+    }
+    let flag = format!("    --{}", args::ENV_TEMPLATE_FLAG);
+    let help = "Print a .env template with all keys";
+    rows.push(row([&flag, "", "", "", help]));
+    rows.push(row(["-h, --help", "", "", "", "Print this help"]));
+
+    let mut out = format!("Usage: {} [OPTIONS]", program_name());
+    let meta = |f: &SchemaField| fields.iter().find(|m| m.name == f.name);
+    // the subcommand is the first arg without flag
+    for f in schema.fields() {
+        if meta(f).is_some_and(|m| m.subcommand) {
+            out.push_str(&format!(" <{}>", f.name));
+        }
+    }
+    for f in schema.fields() {
+        if meta(f).is_some_and(|m| m.positional && !m.subcommand) {
+            out.push_str(&positional(f));
+        }
+    }
+    out.push_str("\n\n");
+
+    let width = |column: usize| rows.iter().map(|r| r[column].len()).max().unwrap_or(0);
+    let widths = [width(0), width(1), width(2), width(3)];
+    for [flag, ty, default, env, help] in &rows {
+        let line = format!(
+            "  {flag:w0$}  {ty:w1$}  {default:w2$}  {env:w3$}  {help}",
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// A `.env` file listing all keys, each with its description, type and default. Only required keys
+/// are left uncommented, so the file can be copied to `.env` and filled in.
+pub(crate) fn env_template(schema: &Schema, fields: &[Field], options: &Options) -> String {
+    let mut out = String::new();
+    for r in rows(schema, fields, options) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        if !r.help.is_empty() {
+            out.push_str(&format!("# {}\n", r.help));
+        }
+        match (&r.default, r.required) {
+            (Some(_), _) if r.secret => out.push_str(&format!("# {}, secret\n", r.ty)),
+            (Some(default), _) => {
+                out.push_str(&format!("# {}, default {}\n", r.ty, quoted(default)))
+            }
+            (None, true) => out.push_str(&format!("# {}, required\n", r.ty)),
+            (None, false) => out.push_str(&format!("# {}\n", r.ty)),
+        }
+        // keys of list items and map entries have placeholders like `<N>`
+        let value = r
+            .default
+            .as_deref()
+            .filter(|_| !r.secret)
+            .map_or(String::new(), quoted);
+        if r.required && r.default.is_none() && !r.dotenv.contains('<') {
+            out.push_str(&format!("{}=\n", r.dotenv));
+        } else {
+            out.push_str(&format!("# {}={value}\n", r.dotenv));
+        }
+    }
+    out
+}
+
+/// `value` in double quotes if it would not be read back as is without.
+fn quoted(value: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-.,:/@+=".contains(c);
+    if value.chars().all(plain) {
+        return value.to_string();
+    }
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+struct Table<'a> {
+    fields: &'a [Field],
+    options: &'a Options,
+    rows: Vec<Row>,
+}
+
+impl<'a> Table<'a> {
+    /// Adds a row for `f`, or for each of its fields if it is a struct. `required` is whether all
+    /// structs around `f` are required, `variant` the subcommand `f` belongs to.
+    fn collect(
+        &mut self,
+        f: &SchemaField,
+        path: &mut Vec<&'static str>,
+        required: bool,
+        variant: Option<&str>,
+    ) {
+        path.push(f.name);
+        let meta = Field::top_level(self.fields, path);
+        let default = meta.and_then(|m| m.default_value(&f.schema));
+        let required = required && f.required && default.is_none();
+        let (nested, segment) = match f.schema.unwrap_optional() {
+            Schema::Struct(nested) => (Some(nested.as_slice()), None),
+            Schema::List(item) => (Some(item.fields()).filter(|f| !f.is_empty()), Some("<n>")),
+            Schema::Map(value) => (
+                Some(value.fields()).filter(|f| !f.is_empty()),
+                Some("<key>"),
+            ),
+            _ => (None, None),
+        };
+        if let Some(nested) = nested {
+            path.extend(segment);
+            for f in nested {
+                self.collect(f, path, required, variant);
+            }
+            if segment.is_some() {
+                path.pop();
+            }
+            path.pop();
+            return;
+        }
+
+        let flag = match meta.and_then(|m| m.short) {
+            Some(short) => format!("-{short}, {}", flag(f, meta, path, variant)),
+            None => format!("    {}", flag(f, meta, path, variant)),
+        };
+        self.rows.push(Row {
+            flag,
+            ty: f.schema.type_name(),
+            default: meta.and_then(|m| m.default).map(str::to_string),
+            required,
+            secret: meta.is_some_and(|m| m.secret) || f.schema.is_secret(),
+            env: env_var(meta, path, self.options),
+            dotenv: dotenv_key(meta, path, self.options),
+            help: meta.and_then(|m| m.help).unwrap_or("").to_string(),
+        });
+
+        if let (Some(m), Schema::Enum(variants)) = (meta, f.schema.unwrap_optional()) {
+            if m.subcommand {
+                for v in variants {
+                    path.push(v.name);
+                    for f in v.schema.fields() {
+                        self.collect(f, path, required, Some(v.name));
+                    }
+                    path.pop();
+                }
+            }
+        }
+        path.pop();
+    }
+}
+
+/// The CLI flag of the key at `path`, like `--database.port`, or `<input>` for positional fields.
+/// `meta` are the explicit names of top-level fields, `variant` the subcommand the key belongs to.
+pub(crate) fn flag(
+    f: &SchemaField,
+    meta: Option<&Field>,
+    path: &[&str],
+    variant: Option<&str>,
+) -> String {
+    match meta {
+        Some(m) if m.subcommand => format!("<{}>", f.name),
+        Some(m) if m.positional => positional(f).trim_start().to_string(),
+        Some(Field {
+            long: Some(long), ..
+        }) => format!("--{long}"),
+        // flags after a subcommand leave out its path, like `serve --port`
+        _ => match variant {
+            Some(variant) => format!("{variant} --{}", path[2..].join(".")),
+            None => format!("--{}", path.join(".")),
+        },
+    }
+}
+
+/// The environment variable of the key at `path`, like `APP_DATABASE__PORT`.
+pub(crate) fn env_var(meta: Option<&Field>, path: &[&str], options: &Options) -> String {
+    match meta.and_then(|m| m.env) {
+        Some(env) => env.to_string(),
+        None => {
+            let prefix = options.env_prefix.as_deref().unwrap_or("");
+            format!("{prefix}{}", dotenv_key(meta, path, options)).to_uppercase()
+        }
+    }
+}
+
+/// The key of `path` in `.env` files, like `DATABASE__PORT`, which unlike the environment
+/// variable has no prefix.
+fn dotenv_key(meta: Option<&Field>, path: &[&str], options: &Options) -> String {
+    match meta.and_then(|m| m.env) {
+        Some(env) => env.to_string(),
+        None => path.join(&options.separator).to_uppercase(),
+    }
+}
+
+/// Like ` <input>`, or ` [<files>...]` for a sequence, which may be empty.
+fn positional(f: &SchemaField) -> String {
+    match f.schema.unwrap_optional() {
+        Schema::List(_) => format!(" [<{}>...]", f.name),
+        _ => format!(" <{}>", f.name),
+    }
+}
+
+/// Whether the args before `--` ask for help. `--help` and `-h` are not taken if a field uses them.
+pub(crate) fn requested(args: &[String], fields: &[Field]) -> bool {
+    args.iter()
+        .take_while(|a| *a != "--")
+        .any(|a| match a.as_str() {
+            "--help" => !fields.iter().any(|f| f.long.unwrap_or(f.name) == "help"),
+            "-h" => Field::by_short(fields, 'h').is_none(),
+            _ => false,
+        })
+}
+
+/// Whether the args before `--` ask for the `.env` template, unless a field uses the flag.
+pub(crate) fn template_requested(args: &[String], fields: &[Field]) -> bool {
+    let flag = format!("--{}", args::ENV_TEMPLATE_FLAG);
+    args.iter().take_while(|a| *a != "--").any(|a| *a == flag)
+        && !fields
+            .iter()
+            .any(|f| f.long.unwrap_or(f.name) == args::ENV_TEMPLATE_FLAG)
+}
+
+fn program_name() -> String {
+    env::args_os()
+        .next()
+        .as_deref()
+        .and_then(|arg0| Path::new(arg0).file_name())
+        .map_or("app".into(), |name| name.to_string_lossy().into_owned())
+}
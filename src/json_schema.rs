@@ -0,0 +1,299 @@
+//! A JSON Schema of the values a type accepts, see
+//! [`FromEnvBuilder::json_schema`](crate::FromEnvBuilder::json_schema).
+
+use serde_json::{json, Map, Number, Value};
+
+use crate::{
+    de::parse_bool,
+    help,
+    schema::{Schema, SchemaField, Variant},
+    Constraint, Field, Options, Relation,
+};
+
+/// The schema of a JSON config file with the fields of `schema`, annotated with the names of the
+/// keys in the other sources.
+pub(crate) fn json_schema(
+    schema: &Schema,
+    fields: &[Field],
+    options: &Options,
+    title: &str,
+) -> Value {
+    let walker = Walker { fields, options };
+    let mut root = walker.object(schema.fields(), &mut Vec::new(), None);
+    walker.relations(schema, &mut root);
+    root.insert(
+        "$schema".into(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    root.insert("title".into(), json!(title));
+    Value::Object(root)
+}
+
+struct Walker<'a> {
+    fields: &'a [Field],
+    options: &'a Options,
+}
+
+impl<'a> Walker<'a> {
+    fn object(
+        &self,
+        fields: &[SchemaField],
+        path: &mut Vec<&'static str>,
+        variant: Option<&str>,
+    ) -> Map<String, Value> {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for f in fields {
+            // a default fills in a field left out of the config file
+            let default = path.is_empty()
+                && self
+                    .fields
+                    .iter()
+                    .any(|m| m.name == f.name && m.default_value(&f.schema).is_some());
+            if f.required && !default {
+                required.push(json!(f.name));
+            }
+            path.push(f.name);
+            properties.insert(f.name.into(), Value::Object(self.field(f, path, variant)));
+            path.pop();
+        }
+        let mut object = Map::new();
+        object.insert("type".into(), json!("object"));
+        object.insert("properties".into(), Value::Object(properties));
+        if !required.is_empty() {
+            object.insert("required".into(), Value::Array(required));
+        }
+        if self.options.strict {
+            object.insert("additionalProperties".into(), json!(false));
+        }
+        object
+    }
+
+    /// The schema of `f` with its description, default, names and constraints.
+    fn field(
+        &self,
+        f: &SchemaField,
+        path: &mut Vec<&'static str>,
+        variant: Option<&str>,
+    ) -> Map<String, Value> {
+        let meta = Field::top_level(self.fields, path);
+        let mut schema = self.schema(&f.schema, path, variant, meta);
+        if let Some(help) = meta.and_then(|m| m.help) {
+            schema.insert("description".into(), json!(help));
+        }
+        if meta.is_some_and(|m| m.secret) || f.schema.is_secret() {
+            schema.insert("x-secret".into(), json!(true));
+        } else if let Some(default) = meta.and_then(|m| m.default) {
+            let default = typed(default, &f.schema, self.options);
+            schema.insert("default".into(), default);
+        }
+        // structs and lists of structs are given by the keys of their fields
+        let nested = match f.schema.unwrap_optional() {
+            Schema::Struct(_) => true,
+            Schema::List(item) | Schema::Map(item) => !item.fields().is_empty(),
+            _ => false,
+        };
+        if !nested {
+            let env = help::env_var(meta, path, self.options);
+            schema.insert("x-env".into(), json!(env));
+            let flag = help::flag(f, meta, path, variant);
+            schema.insert("x-cli".into(), json!(flag));
+            if let Some(short) = meta.and_then(|m| m.short) {
+                schema.insert("x-cli-short".into(), json!(format!("-{short}")));
+            }
+        }
+        for constraint in meta.map_or(&[][..], |m| m.constraints) {
+            constrain(&mut schema, constraint, &f.schema, self.options);
+        }
+        schema
+    }
+
+    fn schema(
+        &self,
+        schema: &Schema,
+        path: &mut Vec<&'static str>,
+        variant: Option<&str>,
+        meta: Option<&Field>,
+    ) -> Map<String, Value> {
+        let value = match schema {
+            Schema::Any => json!({}),
+            Schema::Scalar(name) => scalar(name),
+            Schema::Optional(inner) | Schema::Secret(inner) => {
+                return self.schema(inner, path, variant, meta)
+            }
+            Schema::List(item) => {
+                path.push("<n>");
+                let items = self.schema(item, path, variant, None);
+                path.pop();
+                json!({ "type": "array", "items": items })
+            }
+            Schema::Map(value) => {
+                path.push("<key>");
+                let values = self.schema(value, path, variant, None);
+                path.pop();
+                json!({ "type": "object", "additionalProperties": values })
+            }
+            Schema::Struct(fields) => return self.object(fields, path, variant),
+            Schema::Enum(variants) => {
+                let subcommand = meta.is_some_and(|m| m.subcommand);
+                self.variants(variants, path, variant, subcommand)
+            }
+        };
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!("schemas are objects"),
+        }
+    }
+
+    /// Unit variants as strings, others as an object with the variant as its only key, like in
+    /// `COMMAND=migrate` and `COMMAND__SERVE__PORT=80`.
+    fn variants(
+        &self,
+        variants: &[Variant],
+        path: &mut Vec<&'static str>,
+        variant: Option<&str>,
+        subcommand: bool,
+    ) -> Value {
+        let mut units = Vec::new();
+        let mut one_of = Vec::new();
+        for v in variants {
+            if v.schema == Schema::Struct(Vec::new()) {
+                units.push(json!(v.name));
+                continue;
+            }
+            path.push(v.name);
+            let variant = if subcommand { Some(v.name) } else { variant };
+            let data = self.schema(&v.schema, path, variant, None);
+            path.pop();
+            one_of.push(json!({
+                "type": "object",
+                "properties": { v.name: data },
+                "required": [v.name],
+                "additionalProperties": false,
+            }));
+        }
+        if !units.is_empty() {
+            one_of.insert(0, json!({ "type": "string", "enum": units }));
+        }
+        match one_of.len() {
+            1 => one_of.remove(0),
+            _ => json!({ "oneOf": one_of }),
+        }
+    }
+
+    /// The [`Relation`]s of the fields of the type, as keywords of the root object.
+    fn relations(&self, schema: &Schema, root: &mut Map<String, Value>) {
+        let mut dependent = Map::new();
+        let mut all_of = Vec::new();
+        for field in self.fields {
+            for relation in field.relations {
+                match *relation {
+                    Relation::Requires(other) => {
+                        let entry = dependent.entry(field.name).or_insert(json!([]));
+                        if let Value::Array(others) = entry {
+                            others.push(json!(other));
+                        }
+                    }
+                    Relation::ConflictsWith(other) => {
+                        all_of.push(json!({ "not": { "required": [field.name, other] } }));
+                    }
+                    Relation::RequiredIf(other, value) => {
+                        let value = match schema.get(other) {
+                            Some(schema) => typed(value, schema, self.options),
+                            None => json!(value),
+                        };
+                        all_of.push(json!({
+                            "if": {
+                                "properties": { other: { "const": value } },
+                                "required": [other],
+                            },
+                            "then": { "required": [field.name] },
+                        }));
+                    }
+                }
+            }
+        }
+        if !dependent.is_empty() {
+            root.insert("dependentRequired".into(), Value::Object(dependent));
+        }
+        if !all_of.is_empty() {
+            root.insert("allOf".into(), Value::Array(all_of));
+        }
+    }
+}
+
+fn scalar(name: &str) -> Value {
+    match name {
+        "bool" => json!({ "type": "boolean" }),
+        "u8" | "u16" | "u32" | "u64" | "u128" => json!({ "type": "integer", "minimum": 0 }),
+        "i8" | "i16" | "i32" | "i64" | "i128" => json!({ "type": "integer" }),
+        "f32" | "f64" => json!({ "type": "number" }),
+        "char" => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+        _ => json!({ "type": "string" }),
+    }
+}
+
+/// Adds the keywords of `constraint`, which apply to the items of lists.
+fn constrain(
+    schema: &mut Map<String, Value>,
+    constraint: &Constraint,
+    shape: &Schema,
+    options: &Options,
+) {
+    let list = matches!(shape.unwrap_optional(), Schema::List(_));
+    if *constraint == Constraint::NonEmpty && list {
+        schema.insert("minItems".into(), json!(1));
+    }
+    let (shape, target) = match (shape.unwrap_optional(), schema.get_mut("items")) {
+        (Schema::List(item), Some(Value::Object(items))) => (item.as_ref(), items),
+        _ => (shape, &mut *schema),
+    };
+    match *constraint {
+        Constraint::Min(min) => {
+            target.insert("minimum".into(), number(min));
+        }
+        Constraint::Max(max) => {
+            target.insert("maximum".into(), number(max));
+        }
+        Constraint::NonEmpty => {
+            if target.get("type") == Some(&json!("string")) {
+                target.insert("minLength".into(), json!(1));
+            }
+        }
+        Constraint::Regex(pattern) => {
+            target.insert("pattern".into(), json!(format!("^(?:{pattern})$")));
+        }
+        Constraint::OneOf(values) => {
+            let values = values.iter().map(|v| typed(v, shape, options)).collect();
+            target.insert("enum".into(), Value::Array(values));
+        }
+        Constraint::PathExists => {
+            target.insert("x-path-exists".into(), json!(true));
+        }
+    }
+}
+
+/// A raw value like a default as the JSON value of its type, or as a string if it is not one.
+fn typed(raw: &str, schema: &Schema, options: &Options) -> Value {
+    match schema.unwrap_optional() {
+        Schema::Scalar("bool") => parse_bool(raw).map_or(json!(raw), Value::Bool),
+        Schema::Scalar(name) if name.starts_with(['i', 'u', 'f']) => {
+            serde_json::from_str::<Number>(raw.trim()).map_or(json!(raw), Value::Number)
+        }
+        Schema::List(_) if raw.is_empty() => json!([]),
+        Schema::List(item) => raw
+            .split(options.list_delimiter)
+            .map(|raw| typed(raw, item, options))
+            .collect(),
+        _ => json!(raw),
+    }
+}
+
+/// Whole numbers without fraction, like `1` instead of `1.0`.
+fn number(x: f64) -> Value {
+    if x.fract() == 0.0 && x.abs() < 2f64.powi(53) {
+        json!(x as i64)
+    } else {
+        json!(x)
+    }
+}
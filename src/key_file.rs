@@ -0,0 +1,144 @@
+//! Values read from files named by keys with a `_file` suffix, the convention for secrets mounted
+//! as files by Docker and Kubernetes: `DB_PASSWORD_FILE=/run/secrets/db_password` populates
+//! `db_password` with the content of the file.
+//!
+//! Fields with explicit names are named by those: `DB_URL_FILE` and `--db-file` for
+//! `#[from_env(env = "DB_URL", long = "db")] database_url`, but not `DATABASE_URL_FILE`.
+
+use std::fs;
+
+use crate::{node::Node, schema::Schema, Error, Field, Options, Origin, Raw, Source};
+
+/// The key of `var` if it names the file of a field with an explicit `env` name, like
+/// `database_url_file` for `DB_URL_FILE`.
+pub(crate) fn env_key(fields: &[Field], var: &str) -> Option<String> {
+    let env = strip_suffix_ignore_case(var, "_file")?;
+    let field = Field::by_env(fields, env)?;
+    Some(format!("{}_file", field.name))
+}
+
+/// The key of the flag `--flag` if it names the file of a field with an explicit `long` name,
+/// like `database_url_file` for `--db-file`.
+pub(crate) fn arg_key(fields: &[Field], flag: &str) -> Option<String> {
+    let long = strip_suffix_ignore_case(flag, "-file")
+        .or_else(|| strip_suffix_ignore_case(flag, "_file"))?;
+    let field = Field::by_long(fields, long)?;
+    Some(format!("{}_file", field.name))
+}
+
+/// The name of the file key of the field that `key` names by the name `explicit` replaced, like
+/// `DB_URL_FILE` for `database_url_file` if the field `database_url` has `env = "DB_URL"`.
+pub(crate) fn renamed(
+    fields: &[Field],
+    key: &str,
+    explicit: impl Fn(&Field) -> Option<&'static str>,
+) -> Option<String> {
+    ["_file", "-file"].into_iter().find_map(|suffix| {
+        let target = strip_suffix_ignore_case(key, suffix)?;
+        let name = Field::renamed(fields, target, &explicit)?;
+        Some(format!("{name}{}", &key[target.len()..]))
+    })
+}
+
+/// Replaces the keys `key_file` and `key-file` by the content of the file they name, as the value
+/// of `key`. This only happens if `key` is a field and `key_file` is not.
+///
+/// The content overrides the value of `key` if it was given by the same or an earlier source, so
+/// `DB_PASSWORD` on the CLI wins over `DB_PASSWORD_FILE` in the environment, but not the other way
+/// round.
+pub(crate) fn read(node: &mut Node, schema: &Schema, options: &Options) -> Result<(), Error> {
+    let mut errors = Vec::new();
+    read_children(node, schema, "", options, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::from_many(errors))
+    }
+}
+
+fn read_children(
+    node: &mut Node,
+    schema: &Schema,
+    path: &str,
+    options: &Options,
+    errors: &mut Vec<Error>,
+) {
+    let join = |segment: &str| {
+        if path.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{path}.{segment}")
+        }
+    };
+    let keys: Vec<String> = node.children.keys().cloned().collect();
+    for k in keys {
+        let target = strip_suffix_ignore_case(&k, "_file")
+            .or_else(|| strip_suffix_ignore_case(&k, "-file"))
+            .filter(|target| {
+                schema.get(&join(k.as_str())).is_none() && schema.get(&join(target)).is_some()
+            });
+        let Some(target) = target else {
+            let child = node.children.get_mut(&k).expect("key of the node");
+            read_children(child, schema, &join(&k), options, errors);
+            continue;
+        };
+        let Some(raw) = node
+            .children
+            .remove(&k)
+            .and_then(|n| n.values.last().cloned())
+        else {
+            continue;
+        };
+        let content = match fs::read_to_string(&raw.value) {
+            Ok(content) => content,
+            Err(e) => {
+                errors.push(Error::Invalid {
+                    key: join(target),
+                    value: raw.value,
+                    origin: raw.origin,
+                    message: format!("could not read file: {e}"),
+                });
+                continue;
+            }
+        };
+        let overrides = node
+            .get([target])
+            .and_then(Node::value)
+            .is_none_or(|given| {
+                precedence(&given.origin, options) <= precedence(&raw.origin, options)
+            });
+        if overrides {
+            let value = Raw {
+                value: content.trim_end_matches(['\n', '\r']).to_string(),
+                origin: Origin::KeyFile {
+                    path: raw.value.into(),
+                    from: Box::new(raw.origin),
+                },
+            };
+            node.insert([target], vec![value]);
+        }
+    }
+}
+
+/// Values from sources of higher precedence override the ones from lower precedence.
+fn precedence(origin: &Origin, options: &Options) -> usize {
+    let source = match origin {
+        Origin::File { .. } => Source::File,
+        Origin::Dotenv { .. } => Source::Dotenv,
+        Origin::Env { .. } => Source::Env,
+        Origin::Arg { .. } | Origin::Positional { .. } => Source::Args,
+        Origin::KeyFile { from, .. } => return precedence(from, options),
+        Origin::Default => return 0,
+    };
+    options
+        .sources
+        .iter()
+        .position(|s| *s == source)
+        .map_or(0, |i| i + 1)
+}
+
+fn strip_suffix_ignore_case<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = s.len().checked_sub(suffix.len())?;
+    let (head, tail) = (s.get(..split)?, s.get(split..)?);
+    (tail.eq_ignore_ascii_case(suffix) && !head.is_empty()).then_some(head)
+}
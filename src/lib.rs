@@ -2,7 +2,8 @@
 //!
 //! Intended to be used like this:
 //!
-//! ```no_run
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
 //! use from_env::FromEnv;
 //! use lazy_static::lazy_static;
 //! use serde::Deserialize;
@@ -15,7 +16,7 @@
 //!     "127.0.0.1:8080".into()
 //! }
 //!
-//! #[derive(Debug, Clone, Deserialize)]
+//! #[derive(Debug, Clone, Deserialize, FromEnv)]
 //! pub struct Constants {
 //!     #[serde(default = "cred_file")]
 //!     pub cred_file: String,
@@ -29,8 +30,8 @@
 //! }
 //! ```
 //!
-//! Now you can either provide values for `cred_file` and `server_url` via CLI or .env file, or a mix of both. Any value can be left out.
-//! CLI values override .env files, which in turn override defaults.
+//! Now you can either provide values for `cred_file` and `server_url` via CLI, environment variables or .env file, or a mix of them. Any value can be left out.
+//! CLI values override environment variables, which override .env files, which override config files, which in turn override defaults.
 //!
 //! ### with a `.env` file:
 //!
@@ -38,122 +39,895 @@
 //! cred_file = credentials.json
 //! ```
 //!
+//! The usual dotenv syntax is supported: comments, `export` in front of keys, and single- or
+//! double-quoted values, which may span multiple lines. Double-quoted values can contain the
+//! escape sequences `\n`, `\r`, `\t`, `\"`, `\\` and `\$`. Keys with an empty value like `PORT=`
+//! are skipped, so defaults apply.
+//!
+//! ```txt
+//! # database
+//! export DATABASE_URL=postgres://u:p@h/db?sslmode=require # comment
+//! GREETING="Hello\nWorld"
+//! PATTERN='^\d+'
+//! ```
+//!
+//! Values can reference other variables with `${VAR}`, `$VAR` or `${VAR:-default}`. These resolve
+//! to earlier keys of the same file, environment variables (if they are a source, see
+//! [`FromEnvBuilder::env_vars`]) or values from the other sources, in that order. Single-quoted
+//! values are taken literally, so `PASS='pa$word'` keeps its `$`, unless turned on with
+//! [`FromEnvBuilder::expand_single_quoted`]. `\$` escapes a `$` in double-quoted values.
+//!
+//! ```txt
+//! DATA_DIR=${HOME}/data
+//! API_URL=$BASE_URL/api
+//! LOG_LEVEL=${LOG_LEVEL:-info}
+//! ```
+//!
+//! The `.env` file is read from the current directory, or from the closest parent directory
+//! within the git repository with [`FromEnvBuilder::search_parents`]. Another file can be chosen
+//! with `--env-file path` or `DOTENV_PATH=path`, and [`FromEnvBuilder::dotenv_files`] tells which
+//! ones are read.
+//!
+//! With [`FromEnvBuilder::dotenv_layers`], shared defaults in `.env` can be overridden per
+//! developer and per stage. The stage is taken from `APP_ENV` or set with
+//! [`FromEnvBuilder::app_env`], and the files are read in this order, later ones overriding
+//! earlier ones:
+//!
+//! ```txt
+//! .env
+//! .env.local
+//! .env.production
+//! .env.production.local
+//! ```
+//!
+//! ### with environment variables:
+//!
+//! ```txt
+//! SERVER_URL=localhost://8080 cargo run
+//! ```
+//!
+//! Environment variable names are matched case-insensitively.
+//!
+//! Secrets mounted as files, like Docker and Kubernetes do, are read by adding `_FILE` to the
+//! name. The content of the file populates the field, without trailing newline:
+//!
+//! ```txt
+//! DB_PASSWORD_FILE=/run/secrets/db_password cargo run
+//! ```
+//!
+//! The same works in `.env` files, and on the CLI with `--db_password-file`. Fields with an explicit
+//! `env` or `long` name are read from files named by that, like `DB_URL_FILE` or `--db-file`.
+//! Fields whose name ends in `_file` themselves are read as usual. Turn this off with
+//! [`FromEnvBuilder::key_files`].
+//!
 //! ### or directly in the CLI:
 //!
 //! ```txt
 //! cargo run -- --server_url localhost://8080
 //! ```
+//!
+//! Values can also be attached with `=`, like `--server_url=localhost://8080`. A flag without value
+//! is `true`, and `--no-verbose` sets the bool field `verbose` to `false`. Fields with a short flag
+//! (see below) can be given like `-p 8080` or `-p8080`, and short flags of bool fields can be
+//! bundled, like `-vq`. Everything after `--` is not read as a flag.
+//!
+//! ### or in a config file:
+//!
+//! With the cargo features `toml`, `yaml` or `json`, values can also come from a config file,
+//! given with `--config config.toml` or [`FromEnvBuilder::config_file`]. Tables populate nested
+//! structs, and the file is overridden by all other sources.
+//!
+//! ```toml
+//! server_url = "localhost://8080"
+//!
+//! [database]
+//! port = 5432
+//! ```
+//!
+//! ### naming fields
+//!
+//! By default a field is read from the key matching its name (after `#[serde(rename)]`). The
+//! `#[from_env(...)]` attribute gives it explicit names instead:
+//!
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use from_env::FromEnv;
+//! # use serde::Deserialize;
+//! #[derive(Deserialize, FromEnv)]
+//! struct Constants {
+//!     /// Connection string of the main database.
+//!     #[from_env(env = "DATABASE_URL", long = "db", short = 'd', secret)]
+//!     database_url: String,
+//!     #[from_env(default = "127.0.0.1:8080", help = "Address to listen on")]
+//!     server_url: String,
+//! }
+//! ```
+//!
+//! Here `database_url` is read from `DATABASE_URL` in `.env` files and the environment, regardless
+//! of [`FromEnvBuilder::env_prefix`], and from `--db` or `-d` on the CLI, but not from
+//! `--database_url`. `help` defaults to the doc comment of the field, and `secret` marks values
+//! that should not show up in output. `default = "..."` gives a raw value that is used if no
+//! source has one. Types that are only `Deserialize` can use `impl FromEnv for Constants {}`
+//! instead of the derive.
+//!
+//! ### positional arguments and subcommands
+//!
+//! Fields marked `positional` are read from CLI args without flag, in the order of the fields, a
+//! sequence taking all remaining ones, which may be none. An enum field marked `subcommand` gets
+//! its variant from the first arg without flag, and flags after it populate the fields of the
+//! variant:
+//!
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use from_env::FromEnv;
+//! # use serde::Deserialize;
+//! #[derive(Deserialize)]
+//! #[serde(rename_all = "snake_case")]
+//! enum Command {
+//!     Serve { port: u16 },
+//!     Migrate { #[serde(default)] dry_run: bool },
+//! }
+//!
+//! #[derive(Deserialize, FromEnv)]
+//! struct Cli {
+//!     #[from_env(subcommand)]
+//!     command: Command,
+//!     #[from_env(positional)]
+//!     files: Vec<String>,
+//! }
+//! ```
+//!
+//! ```txt
+//! mytool serve --port 80
+//! mytool migrate --dry_run a.sql b.sql
+//! ```
+//!
+//! In `.env` files and environment variables, the variant is given the same way as nested keys,
+//! like `COMMAND__SERVE__PORT=80`, or as a value if it needs no fields, like `COMMAND=migrate`.
+//!
+//! ### `--help`
+//!
+//! `--help` or `-h` on the CLI print a table of all keys and exit, see [`FromEnvBuilder::usage`]:
+//!
+//! ```txt
+//! Usage: server [OPTIONS]
+//!
+//!   OPTION            TYPE    DEFAULT         ENV           DESCRIPTION
+//!   -d, --db          string  required        DATABASE_URL  Connection string of the main database.
+//!       --server_url  string  127.0.0.1:8080  SERVER_URL    Address to listen on
+//!   -h, --help                                              Print this help
+//! ```
+//!
+//! ### `.env` template
+//!
+//! `--print-env-template` prints a `.env` file listing all keys, with their description, type and
+//! default, and exits. Checked in as `.env.example`, it tells newcomers what to fill in. The same
+//! template is returned by [`FromEnvBuilder::env_template`]:
+//!
+//! ```sh
+//! # Connection string of the main database.
+//! # string, required
+//! DATABASE_URL=
+//!
+//! # Address to listen on
+//! # string, default 127.0.0.1:8080
+//! # SERVER_URL=127.0.0.1:8080
+//! ```
+//!
+//! ### JSON Schema
+//!
+//! With the `schema` feature, `FromEnvBuilder::json_schema` describes what a type accepts as a
+//! JSON Schema, for tools validating deployment manifests. It has the types, defaults, required
+//! fields, enum variants, nested objects, constraints and relations, and the names of each key in
+//! the other sources as `x-env`, `x-cli` and `x-cli-short`:
+//!
+//! ```json
+//! "port": {
+//!   "type": "integer",
+//!   "minimum": 0,
+//!   "default": 8080,
+//!   "x-env": "PORT",
+//!   "x-cli": "--port",
+//!   "x-cli-short": "-p"
+//! }
+//! ```
+//!
+//! ### configuring the sources
+//!
+//! [`FromEnv::builder`] lets you choose which sources are read, their precedence and their inputs:
+//!
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use from_env::{FromEnv, Source};
+//! # #[derive(serde::Deserialize, FromEnv)]
+//! # struct Constants { server_url: String }
+//! let constants = Constants::builder()
+//!     .dotenv("config/.env.prod")
+//!     // reads `APP_SERVER_URL` into `server_url`
+//!     .env_prefix("APP_")
+//!     // CLI args are not read, .env values override environment variables
+//!     .sources([Source::Env, Source::Dotenv])
+//!     .build();
+//! ```
+//!
+//! In tests, [`FromEnv::from_args`], [`FromEnv::from_dotenv_str`] and [`FromEnv::from_map`] read
+//! exactly the given input, without touching the `.env` file or the environment of the process:
+//!
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use from_env::FromEnv;
+//! # #[derive(serde::Deserialize, FromEnv)]
+//! # struct Constants { server_url: String }
+//! let constants = Constants::from_args(["--server_url", "localhost:8080"])?;
+//! assert_eq!(constants.server_url, "localhost:8080");
+//!
+//! let constants = Constants::from_dotenv_str("SERVER_URL=localhost:8080")?;
+//! assert_eq!(constants.server_url, "localhost:8080");
+//! # Ok::<(), from_env::Error>(())
+//! ```
+//!
+//! ### nested structs
+//!
+//! Fields of nested structs are addressed by joining the field names with `__` in `.env` files and
+//! environment variables (configurable via [`FromEnvBuilder::separator`]) and with `.` on the CLI:
+//!
+//! ```txt
+//! DATABASE__HOST=localhost cargo run -- --database.port 5432
+//! ```
+//!
+//! `#[from_env(...)]` attributes only apply to the fields of the type that is read. On the fields
+//! of a nested struct they are ignored, even if it derives [`FromEnv`] itself, so nested keys
+//! follow the field names, and checks of nested values belong in [`Validate`].
+//!
+//! ### lists and maps
+//!
+//! Sequences like `Vec<String>` or `HashSet<u16>` can be given as comma-separated values, by
+//! repeating a CLI flag or with indexed keys. Maps like `HashMap<String, String>` can be given as
+//! comma-separated `key=value` pairs or with nested keys. The delimiters are configurable via
+//! [`FromEnvBuilder::list_delimiter`] and [`FromEnvBuilder::pair_delimiter`].
+//!
+//! ```txt
+//! ALLOWED_HOSTS=a,b,c
+//! PORTS__0=80
+//! PORTS__1=443
+//! LABELS=team=core,tier=1
+//! ```
+//!
+//! ```txt
+//! cargo run -- --allowed_hosts a --allowed_hosts b --labels.team core
+//! ```
+//!
+//! ### secrets
+//!
+//! Wrapping a field in [`Secret`] keeps it out of logs: it prints as `***` and errors leave out
+//! its value. It is read with [`Secret::expose`] and zeroed in memory when dropped.
+//!
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use from_env::{FromEnv, Secret};
+//! #[derive(Debug, serde::Deserialize, FromEnv)]
+//! struct Constants {
+//!     api_key: Secret<String>,
+//! }
+//! ```
+//!
+//! ### validation
+//!
+//! [`Constraint`]s on fields check the raw values, and violations are reported together with
+//! parse errors. The `regex` constraint needs the `regex` feature:
+//!
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use from_env::FromEnv;
+//! #[derive(serde::Deserialize, FromEnv)]
+//! struct Constants {
+//!     #[from_env(min = 1, max = 65535)]
+//!     port: u16,
+//!     #[from_env(non_empty, one_of("debug", "info", "warn"))]
+//!     log_level: String,
+//!     #[from_env(path_exists)]
+//!     data_dir: String,
+//! }
+//! ```
+//!
+//! ```txt
+//! invalid value "0" for `port` (CLI arg --port): must be at least 1
+//! ```
+//!
+//! [`Relation`]s between fields are checked across all sources, and errors name both fields and
+//! where their values came from:
+//!
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use from_env::FromEnv;
+//! #[derive(serde::Deserialize, FromEnv)]
+//! struct Constants {
+//!     #[from_env(default = "false", requires("key_file"))]
+//!     tls: bool,
+//!     #[from_env(required_if("tls", "true"))]
+//!     cert_file: Option<String>,
+//!     key_file: Option<String>,
+//!     #[from_env(conflicts_with = "server_url")]
+//!     socket: Option<String>,
+//!     server_url: Option<String>,
+//! }
+//! ```
+//!
+//! ```txt
+//! 2 errors:
+//!   missing value for `cert_file`, required because `tls` is "true" (.env line 3)
+//!   `socket` (CLI arg --socket) conflicts with `server_url` (.env line 1)
+//! ```
+//!
+//! With `#[from_env(validate)]` on the struct, its [`Validate`] implementation runs after that,
+//! for anything else.
+//!
+//! ### where values came from
+//!
+//! [`FromEnv::from_env_with_report`] also returns a [`Provenance`], telling for each value which
+//! source gave it and which values it overrode. Printed with `{}`, it suits startup logs, as
+//! secrets are left out:
+//!
+//! ```txt
+//! api_key    = ***                 (env var API_KEY, overrides .env line 4)
+//! port       = "9"                 (CLI arg --port, overrides .env line 2, default)
+//! server_url = "http://localhost"  (.env line 1)
+//! ```
+//!
+//! ### reloading
+//!
+//! For long-running services, [`FromEnvBuilder::watch`] returns a [`Watched`] value, which reads
+//! the sources again when the `.env` or config files change. [`Watched::get`] gives a cheap `Arc`
+//! snapshot of the current value and [`Watched::subscribe`] a channel of changes. If the new
+//! values do not parse, the previous value is kept. With the `sighup` feature, `SIGHUP` reloads
+//! too.
+//!
+#![cfg_attr(feature = "derive", doc = "```no_run")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use from_env::FromEnv;
+//! # use std::time::Duration;
+//! # #[derive(serde::Deserialize, FromEnv)]
+//! # struct Constants { server_url: String }
+//! let constants = Constants::builder().watch(Duration::from_secs(2))?;
+//! let server_url = &constants.get().server_url;
+//! # Ok::<(), from_env::Error>(())
+//! ```
+//!
+//! ### errors
+//!
+//! All missing and invalid fields are reported at once in an [`Error`], together with the raw
+//! values and where they were given:
+//!
+//! ```txt
+//! 2 errors:
+//!   invalid value "80a" for `port` (.env line 7): expected u16
+//!   missing value for `database.host`
+//! ```
+//!
+//! Keys that match no field are ignored, unless [`FromEnvBuilder::strict`] is set. Then typos in
+//! `.env` files and CLI args, and in environment variables if a prefix is set, are errors too, as
+//! are undeclared short flags, CLI args no positional field takes, and names of fields replaced by
+//! an explicit `env` or `long`:
+//!
+//! ```txt
+//! unknown key `sever_url` (CLI arg --sever_url), did you mean `server_url`?
+//! ```
 
-use std::{collections::BTreeMap, env};
+use std::{collections::BTreeMap, env, path::PathBuf};
 
-use serde::{de::DeserializeOwned};
+use serde::de::DeserializeOwned;
 
-use serde_json::Value;
+mod args;
+mod builder;
+mod de;
+mod dotenv;
+mod error;
+mod field;
+mod file;
+mod help;
+#[cfg(feature = "schema")]
+mod json_schema;
+mod key_file;
+mod node;
+mod provenance;
+mod schema;
+mod secret;
+mod validate;
+mod watch;
 
-pub trait FromEnv: Sized {
-    fn from_env() -> Result<Self, serde_json::Error>;
-}
+pub use builder::FromEnvBuilder;
+pub use error::{Error, Origin};
+pub use field::Field;
+#[cfg(feature = "derive")]
+pub use from_env_derive::FromEnv;
+use node::{Node, Raw};
+pub use provenance::{Provenance, ProvenanceEntry};
+use schema::Schema;
+pub use secret::Secret;
+pub use validate::{Constraint, Relation, Validate};
+pub use watch::Watched;
+
+/// A type that can be populated from `.env` files, environment variables and CLI args.
+///
+/// Usually derived with `#[derive(FromEnv)]`, but any [`DeserializeOwned`] type can implement it
+/// with an empty `impl FromEnv for Constants {}`.
+pub trait FromEnv: DeserializeOwned {
+    /// Explicit names of the fields, given with `#[from_env(...)]`.
+    fn fields() -> &'static [Field] {
+        &[]
+    }
+
+    /// Reads the `.env` file in the current directory, environment variables and CLI args.
+    fn from_env() -> Result<Self, Error> {
+        Self::builder().build()
+    }
+
+    /// Checks the value after deserializing, which calls [`Validate::validate`] if derived with
+    /// `#[from_env(validate)]`.
+    fn check(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Like [`Self::from_env`], also telling where each value came from.
+    ///
+    #[cfg_attr(feature = "derive", doc = "```no_run")]
+    #[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+    /// # use from_env::FromEnv;
+    /// # #[derive(serde::Deserialize, FromEnv)]
+    /// # struct Constants { server_url: String }
+    /// let (constants, provenance) = Constants::from_env_with_report()?;
+    /// eprintln!("{provenance}");
+    /// # Ok::<(), from_env::Error>(())
+    /// ```
+    fn from_env_with_report() -> Result<(Self, Provenance), Error> {
+        Self::builder().build_with_report()
+    }
+
+    /// Reads the values with the given options instead of the defaults.
+    fn from_env_with(options: &Options) -> Result<Self, Error> {
+        FromEnvBuilder::from(options.clone()).build()
+    }
 
-impl<T> FromEnv for T
-where
-    T: DeserializeOwned,
-{
-    fn from_env() -> Result<Self, serde_json::Error> {
-        let kv = kv_from_dotenv_and_env();
-        let value = kv_to_json_value(kv);
-        serde_json::from_value(value)
+    /// A builder to configure the sources before reading, see [`FromEnvBuilder`].
+    fn builder() -> FromEnvBuilder<Self> {
+        FromEnvBuilder::new()
+    }
+
+    /// Reads only the given CLI args, without the program name. `--help` is not handled.
+    ///
+    /// Like [`Self::from_dotenv_str`] and [`Self::from_map`], this does not touch the `.env` file
+    /// or the environment of the process, which makes it useful in tests.
+    fn from_args<I, S>(args: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::builder()
+            .sources([Source::Args])
+            .args(args)
+            .help(false)
+            .build()
+    }
+
+    /// Reads only the given content of a `.env` file.
+    fn from_dotenv_str(src: &str) -> Result<Self, Error> {
+        Self::builder()
+            .sources([Source::Dotenv])
+            .dotenv_str(src)
+            .build()
+    }
+
+    /// Reads only the given variables, named like environment variables, e.g. `DATABASE__HOST`.
+    fn from_map(vars: BTreeMap<String, String>) -> Result<Self, Error> {
+        Self::builder()
+            .sources([Source::Env])
+            .env_vars(vars)
+            .build()
     }
 }
 
-/// overrides values from dotenv with env
-fn kv_from_dotenv_and_env() -> BTreeMap<String, String> {
-    let mut dotenv = kv_from_dotenv();
-    let env = kv_from_env();
-    for (k, v) in env {
-        dotenv.insert(k, v);
+/// A source of values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The TOML, YAML or JSON config file, see [`Options::config_path`].
+    File,
+    /// The `.env` file.
+    Dotenv,
+    /// Environment variables of the process.
+    Env,
+    /// CLI args.
+    Args,
+}
+
+/// Options for [`FromEnv::from_env_with`], usually set via [`FromEnv::builder`].
+///
+/// More options may be added, so start from [`Options::default`] and set the fields from there.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Options {
+    /// The sources to read from, from lowest to highest precedence.
+    pub sources: Vec<Source>,
+    /// Path of the `.env` file. If it does not exist, it is skipped.
+    pub dotenv_path: PathBuf,
+    /// Path of a TOML, YAML or JSON config file, which can also be given with `--config path` on
+    /// the CLI. Reading each format needs the cargo feature of the same name. Unlike the `.env`
+    /// file, a config file that is given must exist.
+    pub config_path: Option<PathBuf>,
+    /// Whether keys like `DB_PASSWORD_FILE` or `--db_password-file` are read as the path of a file
+    /// holding the value of `db_password`, like secrets mounted by Docker and Kubernetes. The
+    /// trailing newline of the file is removed.
+    pub key_files: bool,
+    /// Whether a relative `dotenv_path` is also looked for in the parent directories of the
+    /// current one, up to the root of the git repository. The closest file is read.
+    pub search_parents: bool,
+    /// Whether the path of the `.env` file can be overridden with `--env-file path` on the CLI or
+    /// the environment variable `DOTENV_PATH`, if those sources are read. Unlike `dotenv_path`,
+    /// a file given this way must exist. Neither populates a field, unless one is named like it.
+    pub dotenv_override: bool,
+    /// Whether `.env.local`, `.env.{app_env}` and `.env.{app_env}.local` are read after the
+    /// `.env` file, each overriding the values of the ones before. They are looked for next to
+    /// it and named after it, so with `config/base.env` the first one is `config/base.env.local`.
+    pub dotenv_layers: bool,
+    /// The stage like `production` choosing the `.env.{app_env}` files of
+    /// [`Options::dotenv_layers`]. If `None`, it is taken from the environment variable `APP_ENV`,
+    /// which does not populate a field, unless one is named like it.
+    pub app_env: Option<String>,
+    /// Content of the `.env` file to read instead of the file at `dotenv_path`, which then only
+    /// names it in errors.
+    pub dotenv_content: Option<String>,
+    /// Environment variables to read instead of the ones of the process.
+    pub env_vars: Option<BTreeMap<String, String>>,
+    /// CLI args to read instead of [`std::env::args`], without the program name.
+    pub args: Option<Vec<String>>,
+    /// Only environment variables starting with this prefix are read, with the prefix stripped.
+    /// E.g. with `MYAPP_`, `MYAPP_SERVER_URL` populates `server_url`.
+    /// If `None`, all environment variables are read.
+    pub env_prefix: Option<String>,
+    /// Separates the path segments of nested keys in `.env` files and environment variables.
+    /// E.g. with `__`, `DATABASE__HOST` populates the field `host` of the field `database`.
+    /// On the CLI, nested keys are always separated by `.`, like `--database.host`.
+    pub separator: String,
+    /// Separates the items of lists, e.g. `ALLOWED_HOSTS=a,b,c` for a `Vec<String>`.
+    pub list_delimiter: char,
+    /// Separates keys from values in the items of maps, e.g. `LABELS=team=core,tier=1` for a `HashMap<String, String>`.
+    pub pair_delimiter: char,
+    /// Whether variable references like `${HOME}` are expanded in single-quoted `.env` values.
+    /// Off by default, so single quotes keep values like passwords as they are.
+    pub expand_single_quoted: bool,
+    /// Whether `--help` or `-h` on the CLI print [`FromEnvBuilder::usage`] and exit the process.
+    pub help: bool,
+    /// Whether keys from `.env` files and CLI args that match no field are an
+    /// [`Error::Unknown`] instead of being ignored, as are undeclared short flags, CLI args left
+    /// over by the positional fields, and names of fields replaced by an explicit `env` or `long`.
+    /// Environment variables are only checked if [`Options::env_prefix`] is set, as they are
+    /// shared with everything else on the system.
+    pub strict: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            sources: vec![Source::File, Source::Dotenv, Source::Env, Source::Args],
+            dotenv_path: ".env".into(),
+            config_path: None,
+            key_files: true,
+            search_parents: false,
+            dotenv_override: true,
+            dotenv_layers: false,
+            app_env: None,
+            dotenv_content: None,
+            env_vars: None,
+            args: None,
+            env_prefix: None,
+            separator: "__".into(),
+            list_delimiter: ',',
+            pair_delimiter: '=',
+            expand_single_quoted: false,
+            help: true,
+            strict: false,
+        }
     }
-    dotenv
 }
 
-fn kv_from_dotenv() -> BTreeMap<String, String> {
-    let Ok(dotenv) = std::fs::read_to_string(".env") else {
-        return Default::default();
-    };
-    let kv_pairs: BTreeMap<String, String> = dotenv
-        .lines()
-        .filter_map(|l| {
-            let trimmed = l.trim();
-
-            let split_eq: Vec<&str> = trimmed.split('=').collect();
-            if trimmed.is_empty() || split_eq.len() != 2 {
-                None
-            } else {
-                let key = split_eq[0].trim();
-                let val = split_eq[1].trim().trim_matches('\'').trim_matches('"');
-                if key.is_empty() || val.is_empty() {
-                    None
-                } else {
-                    Some((key.to_string(), val.to_string()))
+/// values from later sources override values from earlier ones
+fn kv_from_sources(options: &Options, fields: &[Field], schema: &Schema) -> Result<Node, Error> {
+    let mut node = Node::default();
+    // defaults have the lowest precedence
+    for field in fields {
+        let field_schema = schema.get(field.name).unwrap_or(&Schema::Any);
+        if let Some(default) = field.default_value(field_schema) {
+            node.insert([field.name], vec![raw(default, Origin::Default)]);
+        }
+    }
+    // kept to expand variable references once all sources are merged
+    let mut dotenv_files = BTreeMap::new();
+    // keys of renamed fields in strict mode
+    let mut errors = Vec::new();
+    for source in &options.sources {
+        match source {
+            Source::File => {
+                if let Some(path) = config_file(options, fields, schema) {
+                    node.merge(file::read(&path)?);
+                }
+            }
+            Source::Dotenv => {
+                // later files override earlier ones
+                for (path, entries) in kv_from_dotenv(options, fields, schema)? {
+                    // dotenv keys are unique, later ones override earlier ones
+                    let mut kv = BTreeMap::new();
+                    for e in &entries {
+                        let origin = Origin::Dotenv {
+                            path: path.clone(),
+                            line: e.line,
+                        };
+                        match env_key(&e.key, &e.key, fields) {
+                            Ok(key) => {
+                                kv.insert(key, raw("", origin));
+                            }
+                            Err(explicit) if options.strict => {
+                                errors.push(renamed(&e.key, origin, explicit));
+                            }
+                            Err(_) => {}
+                        }
+                    }
+                    node.extend(kv, &options.separator);
+                    dotenv_files.insert(path, entries);
                 }
             }
-        })
-        .collect();
-    kv_pairs
-}
-
-fn kv_from_env() -> BTreeMap<String, String> {
-    let args: Vec<String> = env::args().skip(1).collect();
-    let mut kv: BTreeMap<String, String> = Default::default();
-    let mut kcache: Option<String> = None;
-    for a in args {
-        let k = kcache.take();
-        if a.starts_with("--") {
-            if let Some(k) = k {
-                kv.insert(k, "true".to_string());
+            Source::Env => {
+                let prefix = options.env_prefix.as_deref();
+                let vars = env_vars(options);
+                let env = kv_from_env(vars, prefix, fields, schema, options.strict)?;
+                node.extend(env, &options.separator);
             }
-            // set key:
-            kcache = Some(a[2..].trim_matches('\'').trim_matches('"').to_string());
-        } else {
-            if let Some(k) = k {
-                kv.insert(
-                    k,
-                    a.as_str().trim_matches('\'').trim_matches('"').to_string(),
-                );
-            } else {
-                // ignore values without key
+            Source::Args => {
+                let args = cli_args(options);
+                let kv = args::kv_from_args(&args, fields, schema, options.strict)?;
+                node.extend(kv, ".");
             }
         }
     }
-    if let Some(k) = kcache {
-        kv.insert(k, "true".to_string());
+    if !errors.is_empty() {
+        return Err(Error::from_many(errors));
+    }
+    dotenv::expand(&mut node, &dotenv_files, options)?;
+    if options.key_files {
+        key_file::read(&mut node, schema, options)?;
+    }
+    Ok(node)
+}
+
+fn kv_from_dotenv(
+    options: &Options,
+    fields: &[Field],
+    schema: &Schema,
+) -> Result<Vec<(PathBuf, Vec<dotenv::Entry>)>, Error> {
+    let files = match &options.dotenv_content {
+        Some(src) => vec![(options.dotenv_path.clone(), src.clone())],
+        None => dotenv_files(options, fields, schema)?
+            .into_iter()
+            .filter_map(|path| Some((path.clone(), std::fs::read_to_string(&path).ok()?)))
+            .collect(),
+    };
+    let mut parsed = Vec::new();
+    for (path, src) in files {
+        match dotenv::parse(&src, options.expand_single_quoted) {
+            Ok(entries) => parsed.push((path, entries)),
+            Err(e) => {
+                return Err(Error::Syntax {
+                    origin: Origin::Dotenv { path, line: e.line },
+                    message: e.message,
+                })
+            }
+        }
     }
-    kv
+    Ok(parsed)
 }
 
-fn kv_to_json_value(kv: BTreeMap<String, String>) -> Value {
-    let mut map = serde_json::Map::new();
+/// The `.env` files to read, from lowest to highest precedence: the one given by [`dotenv_file`]
+/// and, with [`Options::dotenv_layers`], the existing ones of `.env.local`, `.env.{app_env}` and
+/// `.env.{app_env}.local` next to it.
+fn dotenv_files(
+    options: &Options,
+    fields: &[Field],
+    schema: &Schema,
+) -> Result<Vec<PathBuf>, Error> {
+    let file = dotenv_file(options, fields, schema)?;
+    let base = file.clone().unwrap_or_else(|| options.dotenv_path.clone());
+    let mut files: Vec<PathBuf> = file.into_iter().collect();
+    let Some(name) = base.file_name().filter(|_| options.dotenv_layers) else {
+        return Ok(files);
+    };
+    let name = name.to_string_lossy();
+    let app_env = options
+        .app_env
+        .clone()
+        .or_else(|| env_var(options, APP_ENV));
+    let mut layers = vec![format!("{name}.local")];
+    if let Some(app_env) = app_env.filter(|e| !e.is_empty()) {
+        layers.push(format!("{name}.{app_env}"));
+        layers.push(format!("{name}.{app_env}.local"));
+    }
+    for layer in layers {
+        let path = base.with_file_name(layer);
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
 
-    for (k, v) in kv {
-        map.insert(k, v_to_json_value(v));
+/// The `.env` file to read, given by `--env-file` or `DOTENV_PATH`, or else found at
+/// [`Options::dotenv_path`]. `None` if there is none.
+fn dotenv_file(
+    options: &Options,
+    fields: &[Field],
+    schema: &Schema,
+) -> Result<Option<PathBuf>, Error> {
+    if options.dotenv_override {
+        let from_args = || {
+            let args = cli_args(options);
+            let path = args::reserved_value(&args, args::ENV_FILE_FLAG, fields, schema)?;
+            Some((path, format!("--{}", args::ENV_FILE_FLAG)))
+        };
+        let from_env = || Some((env_var(options, DOTENV_PATH)?, DOTENV_PATH.to_string()));
+        let reads = |source| options.sources.contains(&source);
+        let path = reads(Source::Args)
+            .then(from_args)
+            .flatten()
+            .or_else(|| reads(Source::Env).then(from_env).flatten());
+        if let Some((path, given_by)) = path {
+            let path = PathBuf::from(path);
+            if !path.is_file() {
+                return Err(Error::Custom {
+                    key: String::new(),
+                    message: format!(
+                        "env file `{}` given by {given_by} does not exist",
+                        path.display()
+                    ),
+                });
+            }
+            return Ok(Some(path));
+        }
     }
+    Ok(dotenv::find(&options.dotenv_path, options.search_parents))
+}
 
-    Value::Object(map)
+/// The config file given by `--config`, or else [`Options::config_path`].
+fn config_file(options: &Options, fields: &[Field], schema: &Schema) -> Option<PathBuf> {
+    let from_args = options
+        .sources
+        .contains(&Source::Args)
+        .then(|| args::reserved_value(&cli_args(options), args::CONFIG_FLAG, fields, schema))
+        .flatten();
+    from_args
+        .map(PathBuf::from)
+        .or_else(|| options.config_path.clone())
 }
 
-fn v_to_json_value(v: String) -> Value {
-    if let Ok(e) = v.parse::<bool>() {
-        Value::Bool(e)
-    } else if let Ok(e) = v.parse::<u64>() {
-        Value::Number(serde_json::Number::from(e))
-    } else if let Ok(e) = v.parse::<i64>() {
-        Value::Number(serde_json::Number::from(e))
-    } else if let Ok(e) = v.parse::<f64>() {
-        if let Some(e) = serde_json::Number::from_f64(e) {
-            Value::Number(e)
-        } else {
-            Value::String(v)
+/// The environment variable overriding the path of the `.env` file, see [`Options::dotenv_override`].
+const DOTENV_PATH: &str = "DOTENV_PATH";
+
+/// The environment variable choosing the stage, see [`Options::app_env`].
+const APP_ENV: &str = "APP_ENV";
+
+/// keys are lowercased, so `SERVER_URL` populates `server_url`
+///
+/// `APP_ENV` and `DOTENV_PATH` configure the sources and are left out, unless a field takes them.
+/// With `strict`, variables with the prefix that name a renamed field are an error. Others are
+/// shared with everything else on the system.
+fn kv_from_env(
+    vars: Vec<(String, String)>,
+    prefix: Option<&str>,
+    fields: &[Field],
+    schema: &Schema,
+    strict: bool,
+) -> Result<BTreeMap<String, Raw>, Error> {
+    let mut kv = BTreeMap::new();
+    let mut errors = Vec::new();
+    for (var, v) in vars {
+        let explicit =
+            Field::by_env(fields, &var).is_some() || key_file::env_key(fields, &var).is_some();
+        let k = match (prefix, explicit) {
+            // explicit names are read regardless of the prefix
+            (_, true) => &var,
+            (Some(prefix), false) => match strip_prefix_ignore_case(&var, prefix) {
+                Some(k) => k,
+                None => continue,
+            },
+            (None, false) => &var,
+        };
+        if k.is_empty() {
+            continue;
+        }
+        match env_key(k, &var, fields) {
+            Ok(k) => {
+                let k = k.to_lowercase();
+                let reserved = [APP_ENV, DOTENV_PATH]
+                    .iter()
+                    .any(|r| r.eq_ignore_ascii_case(&var));
+                if !reserved || schema.get(&k).is_some() {
+                    kv.insert(k, raw(&v, Origin::Env { var }));
+                }
+            }
+            Err(explicit) if strict && prefix.is_some() => {
+                errors.push(renamed(&var, Origin::Env { var: var.clone() }, explicit));
+            }
+            Err(_) => {}
         }
+    }
+    if !errors.is_empty() {
+        return Err(Error::from_many(errors));
+    }
+    Ok(kv)
+}
+
+/// The key for `key`, given as `var` in a `.env` file or the environment, taking the
+/// explicit `env` names of fields into account. `Err` with the explicit name if the key belongs to
+/// a field that is read from another variable.
+fn env_key(key: &str, var: &str, fields: &[Field]) -> Result<String, String> {
+    let explicit = |f: &Field| f.env;
+    if let Some(field) = Field::by_env(fields, var) {
+        Ok(field.name.to_string())
+    } else if let Some(key) = key_file::env_key(fields, var) {
+        Ok(key)
+    } else if let Some(name) = Field::renamed(fields, key, explicit) {
+        Err(name.to_string())
+    } else if let Some(name) = key_file::renamed(fields, key, explicit) {
+        Err(name.to_uppercase())
+    } else {
+        Ok(key.to_string())
+    }
+}
+
+/// The error in strict mode for `key`, the name of a field that is read as `explicit` instead.
+pub(crate) fn renamed(key: &str, origin: Origin, explicit: String) -> Error {
+    Error::Unknown {
+        key: key.to_string(),
+        origin,
+        suggestion: Some(explicit),
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    if head.eq_ignore_ascii_case(prefix) {
+        Some(&s[prefix.len()..])
     } else {
-        Value::String(v)
+        None
+    }
+}
+
+/// the args given in the options, or else the ones of the process
+fn cli_args(options: &Options) -> Vec<String> {
+    match &options.args {
+        Some(args) => args.clone(),
+        None => env::args().skip(1).collect(),
+    }
+}
+
+/// the variables given in the options, or else the ones of the process
+fn env_vars(options: &Options) -> Vec<(String, String)> {
+    match &options.env_vars {
+        Some(vars) => vars.clone().into_iter().collect(),
+        // ignore variables that are not valid unicode
+        None => env::vars_os()
+            .filter_map(|(var, v)| Some((var.into_string().ok()?, v.into_string().ok()?)))
+            .collect(),
+    }
+}
+
+/// the variable given in the options, or else the one of the process
+fn env_var(options: &Options, var: &str) -> Option<String> {
+    match &options.env_vars {
+        Some(vars) => vars.get(var).cloned(),
+        None => env::var(var).ok(),
+    }
+}
+
+fn raw(value: &str, origin: Origin) -> Raw {
+    Raw {
+        value: value.to_string(),
+        origin,
     }
 }
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use crate::Origin;
+
+/// A value as it was given, before parsing.
+#[derive(Debug, Clone)]
+pub(crate) struct Raw {
+    pub value: String,
+    pub origin: Origin,
+}
+
+/// Values collected from all sources, nested along the key paths.
+///
+/// `database__host=localhost` in a `.env` file and `--database.host localhost` on the CLI both end
+/// up as the value of the child `host` of the child `database` of the root node.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Node {
+    /// Usually a single value, but repeated CLI flags like `--host a --host b` give one per flag.
+    pub values: Vec<Raw>,
+    pub children: BTreeMap<String, Node>,
+    /// Values of earlier sources that `values` replaced, for the [`Provenance`](crate::Provenance).
+    pub overridden: Vec<Raw>,
+}
+
+impl Node {
+    /// The value for a scalar, which is the last one given.
+    pub fn value(&self) -> Option<&Raw> {
+        self.values.last()
+    }
+
+    /// The node at the given path, matching segments case-insensitively.
+    pub fn get<'a>(&self, path: impl IntoIterator<Item = &'a str>) -> Option<&Node> {
+        let mut node = self;
+        for segment in path {
+            node = node
+                .children
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(segment))?
+                .1;
+        }
+        Some(node)
+    }
+
+    /// The first value of this node or its descendants matching `f`.
+    pub fn find_value(&self, f: &impl Fn(&Raw) -> bool) -> Option<&Raw> {
+        self.values
+            .iter()
+            .find(|raw| f(raw))
+            .or_else(|| self.children.values().find_map(|child| child.find_value(f)))
+    }
+
+    /// Calls `f` on all values of this node and its descendants, including overridden ones.
+    pub fn try_for_each_value<E>(
+        &mut self,
+        f: &mut impl FnMut(&mut Raw) -> Result<(), E>,
+    ) -> Result<(), E> {
+        for raw in self.values.iter_mut().chain(&mut self.overridden) {
+            f(raw)?;
+        }
+        for child in self.children.values_mut() {
+            child.try_for_each_value(f)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the values at the given path, overriding any values that were there before.
+    ///
+    /// Path segments are matched case-insensitively against existing children, the first spelling wins.
+    pub fn insert<'a>(&mut self, path: impl IntoIterator<Item = &'a str>, values: Vec<Raw>) {
+        let mut node = self;
+        for segment in path {
+            let key = match node
+                .children
+                .keys()
+                .find(|k| k.eq_ignore_ascii_case(segment))
+            {
+                Some(k) => k.clone(),
+                None => segment.to_string(),
+            };
+            node = node.children.entry(key).or_default();
+        }
+        let replaced = std::mem::replace(&mut node.values, values);
+        node.overridden.extend(replaced);
+    }
+
+    /// Merges `other` into this node, its values overriding the ones at the same paths.
+    pub fn merge(&mut self, other: Node) {
+        if !other.values.is_empty() {
+            let replaced = std::mem::replace(&mut self.values, other.values);
+            self.overridden.extend(replaced);
+        }
+        for (segment, child) in other.children {
+            let key = match self
+                .children
+                .keys()
+                .find(|k| k.eq_ignore_ascii_case(&segment))
+            {
+                Some(k) => k.clone(),
+                None => segment,
+            };
+            self.children.entry(key).or_default().merge(child);
+        }
+    }
+
+    /// Inserts all pairs of one source, splitting the keys into paths at `separator`.
+    ///
+    /// Values of repeated keys are collected, while values from earlier sources are overridden.
+    pub fn extend(&mut self, kv: impl IntoIterator<Item = (String, Raw)>, separator: &str) {
+        let mut grouped: BTreeMap<String, Vec<Raw>> = BTreeMap::new();
+        for (k, v) in kv {
+            grouped.entry(k).or_default().push(v);
+        }
+        for (k, values) in grouped {
+            if separator.is_empty() {
+                self.insert([k.as_str()], values);
+            } else {
+                self.insert(k.split(separator), values);
+            }
+        }
+    }
+}
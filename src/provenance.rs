@@ -0,0 +1,175 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    node::Node,
+    schema::{Schema, Variant},
+    Field, Origin,
+};
+
+/// Where the values of a [`FromEnv`](crate::FromEnv) type came from, returned by
+/// [`FromEnv::from_env_with_report`](crate::FromEnv::from_env_with_report).
+///
+/// Only values of fields are listed, keyed by their path like `database.port`. `Display` prints a
+/// line per value for startup logs, with `***` for secrets:
+///
+/// ```txt
+/// api_key    = ***                 (env var API_KEY)
+/// port       = "8080"              (CLI arg --port, overrides .env line 3, default)
+/// server_url = "http://localhost"  (.env line 1)
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    entries: BTreeMap<String, ProvenanceEntry>,
+}
+
+/// A value of a [`Provenance`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct ProvenanceEntry {
+    /// The raw value, before parsing. Values of repeated CLI flags are joined with the list
+    /// delimiter.
+    pub value: String,
+    pub origin: Origin,
+    /// Values given by sources of lower precedence, in the order they were overridden.
+    pub overridden: Vec<(String, Origin)>,
+    /// Whether the field is a [`Secret`](crate::Secret) or marked with `#[from_env(secret)]`.
+    /// `Debug` and `Display` leave out the values then.
+    pub secret: bool,
+}
+
+impl Provenance {
+    pub(crate) fn new(
+        node: &Node,
+        schema: &Schema,
+        fields: &[Field],
+        list_delimiter: char,
+    ) -> Self {
+        let mut provenance = Provenance::default();
+        provenance.collect(node, schema, fields, "", false, list_delimiter);
+        provenance
+    }
+
+    /// The entry of a key like `database.port`.
+    pub fn get(&self, key: &str) -> Option<&ProvenanceEntry> {
+        self.entries.get(key)
+    }
+
+    /// All entries, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ProvenanceEntry)> {
+        self.entries.iter().map(|(k, e)| (k.as_str(), e))
+    }
+
+    fn collect(
+        &mut self,
+        node: &Node,
+        schema: &Schema,
+        fields: &[Field],
+        path: &str,
+        secret: bool,
+        list_delimiter: char,
+    ) {
+        let secret = secret || schema.is_secret();
+        if let (Some(last), false) = (node.values.last(), path.is_empty()) {
+            let values: Vec<&str> = node.values.iter().map(|raw| raw.value.as_str()).collect();
+            let entry = ProvenanceEntry {
+                value: values.join(&list_delimiter.to_string()),
+                origin: last.origin.clone(),
+                overridden: node
+                    .overridden
+                    .iter()
+                    .map(|raw| (raw.value.clone(), raw.origin.clone()))
+                    .collect(),
+                secret,
+            };
+            self.entries.insert(path.to_string(), entry);
+        }
+        for (k, child) in &node.children {
+            // children that are not fields are left out
+            let (name, schema) = match schema.unwrap_optional() {
+                Schema::Struct(schema_fields) => {
+                    match schema_fields
+                        .iter()
+                        .find(|f| f.name.eq_ignore_ascii_case(k))
+                    {
+                        Some(f) => (f.name, &f.schema),
+                        None => continue,
+                    }
+                }
+                Schema::Enum(variants) => match Variant::find(variants, k) {
+                    Some(v) => (v.name, &v.schema),
+                    None => continue,
+                },
+                Schema::List(item) | Schema::Map(item) => (k.as_str(), item.as_ref()),
+                Schema::Any => (k.as_str(), schema),
+                _ => continue,
+            };
+            let marked =
+                path.is_empty() && Field::top_level(fields, &[name]).is_some_and(|f| f.secret);
+            let path = if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{path}.{name}")
+            };
+            self.collect(
+                child,
+                schema,
+                fields,
+                &path,
+                secret || marked,
+                list_delimiter,
+            );
+        }
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |e: &ProvenanceEntry| match e.secret {
+            true => "***".to_string(),
+            false => format!("{:?}", e.value),
+        };
+        let key_width = self.entries.keys().map(|k| k.len()).max().unwrap_or(0);
+        let value_width = self
+            .entries
+            .values()
+            .map(|e| value(e).len())
+            .max()
+            .unwrap_or(0);
+        for (i, (k, e)) in self.entries.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{k:key_width$} = {:value_width$}  ({}",
+                value(e),
+                e.origin
+            )?;
+            for (i, (_, origin)) in e.overridden.iter().rev().enumerate() {
+                let sep = if i == 0 { ", overrides " } else { ", " };
+                write!(f, "{sep}{origin}")?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ProvenanceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redact = |value: &str| match self.secret {
+            true => "***".to_string(),
+            false => value.to_string(),
+        };
+        let overridden: Vec<_> = self
+            .overridden
+            .iter()
+            .map(|(value, origin)| (redact(value), origin))
+            .collect();
+        f.debug_struct("ProvenanceEntry")
+            .field("value", &redact(&self.value))
+            .field("origin", &self.origin)
+            .field("overridden", &overridden)
+            .field("secret", &self.secret)
+            .finish()
+    }
+}
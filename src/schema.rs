@@ -0,0 +1,676 @@
+//! The shape of a type, found by deserializing it from a [`Probe`] that records what the type
+//! asks for.
+//!
+//! The probe hands out zero values like [`Placeholder`] does. Types rejecting those, like a
+//! `SocketAddr` given an empty string, end the pass, so probing starts over with the next value
+//! from [`STRINGS`] for that field. If none is accepted, the field is moved to the end of its
+//! struct, so at least the fields before it are seen.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, VariantAccess, Visitor,
+};
+
+use crate::{
+    de::{Placeholder, PlaceholderVariant},
+    secret, Error,
+};
+
+/// Strings tried in turn for fields that reject the ones before.
+const STRINGS: &[&str] = &[
+    "",
+    "0",
+    "0.0.0.0:0",
+    "0.0.0.0",
+    "http://localhost/",
+    "1s",
+    "a",
+];
+
+/// Probing stops going deeper here, for recursive types like `struct Tree { children: Vec<Tree> }`.
+const MAX_DEPTH: usize = 16;
+
+/// The shape of a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Schema {
+    /// Anything, or not known.
+    Any,
+    /// A single value like `bool`, `u16` or `string`.
+    Scalar(&'static str),
+    Optional(Box<Schema>),
+    /// A [`Secret`](crate::Secret), with the shape of its value.
+    Secret(Box<Schema>),
+    List(Box<Schema>),
+    /// A map with string keys and values of the given shape.
+    Map(Box<Schema>),
+    Struct(Vec<SchemaField>),
+    Enum(Vec<Variant>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SchemaField {
+    pub name: &'static str,
+    pub schema: Schema,
+    /// Whether deserialization fails without a value, i.e. the field has no default and is not an `Option`.
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Variant {
+    pub name: &'static str,
+    /// The data of the variant, an empty struct for unit variants.
+    pub schema: Schema,
+}
+
+impl Schema {
+    pub fn of<T: DeserializeOwned>() -> Schema {
+        let mut schema = Schema::Any;
+        let mut pass = Pass::default();
+        // each variant of an enum takes its own passes
+        let mut selections = vec![None];
+        let mut selected = BTreeSet::new();
+        while let Some(select) = selections.pop() {
+            pass.select = select;
+            loop {
+                let (probed, result) = probe::<T>(&pass);
+                schema.merge(probed);
+                let Some(key) = result
+                    .err()
+                    .as_ref()
+                    .and_then(Error::key)
+                    .map(str::to_string)
+                else {
+                    break;
+                };
+                let attempt = pass.attempts.entry(key.clone()).or_default();
+                *attempt += 1;
+                if *attempt >= STRINGS.len() && !pass.failed.insert(key) {
+                    break;
+                }
+            }
+            let mut paths = Vec::new();
+            schema.variant_paths("", &mut paths);
+            for path in paths {
+                if selected.insert(path.clone()) {
+                    selections.push(Some(path));
+                }
+            }
+        }
+        // a field is required if leaving it out fails
+        let mut paths = Vec::new();
+        schema.field_paths("", &mut paths);
+        for path in paths {
+            pass.omit = Some(path.clone());
+            pass.select = Some(path.clone());
+            let (_, result) = probe::<T>(&pass);
+            if matches!(result, Err(Error::Missing { key }) if key == path) {
+                schema.set_required(&path);
+            }
+        }
+        schema
+    }
+
+    /// The schema inside `Option`s and `Secret`s.
+    pub fn unwrap_optional(&self) -> &Schema {
+        match self {
+            Schema::Optional(inner) | Schema::Secret(inner) => inner.unwrap_optional(),
+            schema => schema,
+        }
+    }
+
+    /// Whether this is a `Secret`, maybe inside an `Option`.
+    pub fn is_secret(&self) -> bool {
+        match self {
+            Schema::Optional(inner) => inner.is_secret(),
+            Schema::Secret(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The schema at a path of struct fields and enum variants like `database.port`, matching
+    /// names case-insensitively.
+    pub fn get(&self, path: &str) -> Option<&Schema> {
+        path.split('.')
+            .try_fold(self, |schema, segment| match schema.unwrap_optional() {
+                Schema::Enum(variants) => Some(&Variant::find(variants, segment)?.schema),
+                schema => {
+                    let field = schema
+                        .fields()
+                        .iter()
+                        .find(|f| f.name.eq_ignore_ascii_case(segment))?;
+                    Some(&field.schema)
+                }
+            })
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self.unwrap_optional(), Schema::Scalar("bool"))
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self.unwrap_optional(), Schema::List(_))
+    }
+
+    /// The fields of a struct, or none.
+    pub fn fields(&self) -> &[SchemaField] {
+        match self.unwrap_optional() {
+            Schema::Struct(fields) => fields,
+            _ => &[],
+        }
+    }
+
+    /// Fills in the parts `self` does not know yet from `other`.
+    fn merge(&mut self, other: Schema) {
+        match (self, other) {
+            (_, Schema::Any) => {}
+            (this @ Schema::Any, other) => *this = other,
+            (Schema::Optional(a), Schema::Optional(b))
+            | (Schema::Secret(a), Schema::Secret(b))
+            | (Schema::List(a), Schema::List(b))
+            | (Schema::Map(a), Schema::Map(b)) => a.merge(*b),
+            (Schema::Struct(a), Schema::Struct(b)) => {
+                for (a, b) in a.iter_mut().zip(b) {
+                    a.schema.merge(b.schema);
+                }
+            }
+            (Schema::Enum(a), Schema::Enum(b)) => {
+                for (a, b) in a.iter_mut().zip(b) {
+                    a.schema.merge(b.schema);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Paths of all enum variants, like `command.serve`.
+    fn variant_paths(&self, path: &str, paths: &mut Vec<String>) {
+        match self {
+            Schema::Optional(inner) | Schema::Secret(inner) => inner.variant_paths(path, paths),
+            Schema::List(inner) => inner.variant_paths(&join(path, "0"), paths),
+            Schema::Map(inner) => inner.variant_paths(&join(path, "*"), paths),
+            Schema::Struct(fields) => {
+                for f in fields {
+                    f.schema.variant_paths(&join(path, f.name), paths);
+                }
+            }
+            Schema::Enum(variants) => {
+                for v in variants {
+                    let path = join(path, v.name);
+                    v.schema.variant_paths(&path, paths);
+                    paths.push(path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Paths of all struct fields, in the form of the keys of [`Error`]s.
+    fn field_paths(&self, path: &str, paths: &mut Vec<String>) {
+        match self {
+            Schema::Optional(inner) | Schema::Secret(inner) => inner.field_paths(path, paths),
+            Schema::List(inner) => inner.field_paths(&join(path, "0"), paths),
+            Schema::Map(inner) => inner.field_paths(&join(path, "*"), paths),
+            Schema::Struct(fields) => {
+                for f in fields {
+                    let path = join(path, f.name);
+                    f.schema.field_paths(&path, paths);
+                    paths.push(path);
+                }
+            }
+            Schema::Enum(variants) => {
+                for v in variants {
+                    v.schema.field_paths(&join(path, v.name), paths);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn set_required(&mut self, path: &str) {
+        let (segment, rest) = path.split_once('.').unwrap_or((path, ""));
+        match self {
+            Schema::Optional(inner) | Schema::Secret(inner) => inner.set_required(path),
+            Schema::List(inner) | Schema::Map(inner) if !rest.is_empty() => {
+                inner.set_required(rest)
+            }
+            Schema::Struct(fields) => {
+                if let Some(f) = fields.iter_mut().find(|f| f.name == segment) {
+                    if rest.is_empty() {
+                        f.required = true;
+                    } else {
+                        f.schema.set_required(rest);
+                    }
+                }
+            }
+            Schema::Enum(variants) => {
+                if let Some(v) = variants.iter_mut().find(|v| v.name == segment) {
+                    v.schema.set_required(rest);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Variant {
+    /// The variant named `name`, ignoring case.
+    pub fn find<'a>(variants: &'a [Variant], name: &str) -> Option<&'a Variant> {
+        variants.iter().find(|v| v.name.eq_ignore_ascii_case(name))
+    }
+}
+
+fn join(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{path}.{segment}")
+    }
+}
+
+fn probe<T: DeserializeOwned>(pass: &Pass) -> (Schema, Result<T, Error>) {
+    let slot = RefCell::new(Schema::Any);
+    let result = T::deserialize(Probe {
+        path: String::new(),
+        depth: 0,
+        slot: &slot,
+        pass,
+    });
+    (slot.into_inner(), result)
+}
+
+/// What a pass does differently, keyed by paths of fields.
+#[derive(Default)]
+struct Pass {
+    /// Left out, to see if it is required.
+    omit: Option<String>,
+    /// A path like `command.serve`, choosing the variants of the enums along it. Other enums get
+    /// their first variant.
+    select: Option<String>,
+    /// How many values the field rejected.
+    attempts: BTreeMap<String, usize>,
+    /// Rejected all values, visited last.
+    failed: BTreeSet<String>,
+}
+
+/// Records the shape asked for into `slot`.
+struct Probe<'a> {
+    path: String,
+    depth: usize,
+    slot: &'a RefCell<Schema>,
+    pass: &'a Pass,
+}
+
+impl<'a> Probe<'a> {
+    fn child<'b>(&self, segment: &str, slot: &'b RefCell<Schema>) -> Probe<'b>
+    where
+        'a: 'b,
+    {
+        Probe {
+            path: join(&self.path, segment),
+            depth: self.depth + 1,
+            slot,
+            pass: self.pass,
+        }
+    }
+
+    fn set(&self, schema: Schema) {
+        *self.slot.borrow_mut() = schema;
+    }
+
+    /// The number of values rejected by this field in earlier passes.
+    fn attempt(&self) -> usize {
+        self.pass.attempts.get(&self.path).copied().unwrap_or(0)
+    }
+
+    fn too_deep(&self) -> bool {
+        self.depth >= MAX_DEPTH
+    }
+
+    /// Whether the field at `path` failed before, or contains one that did.
+    fn failed(&self, path: &str) -> bool {
+        self.pass
+            .failed
+            .iter()
+            .any(|f| f == path || f.starts_with(&format!("{path}.")))
+    }
+}
+
+/// Numbers count up with each attempt, so types like `NonZeroU16` get a 1 in the second pass.
+macro_rules! probe_number {
+    ($($method:ident => $visit:ident($ty:ident),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.set(Schema::Scalar(stringify!($ty)));
+                visitor.$visit(self.attempt() as $ty)
+            }
+        )*
+    };
+}
+
+macro_rules! probe_string {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.set(Schema::Scalar("string"));
+                visitor.visit_str(STRINGS[self.attempt().min(STRINGS.len() - 1)])
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for Probe<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        Placeholder.deserialize_any(visitor)
+    }
+
+    probe_number! {
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+        deserialize_f32 => visit_f32(f32),
+        deserialize_f64 => visit_f64(f64),
+    }
+
+    probe_string! {
+        deserialize_str deserialize_string deserialize_identifier deserialize_bytes
+        deserialize_byte_buf
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.set(Schema::Scalar("bool"));
+        visitor.visit_bool(false)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.set(Schema::Scalar("char"));
+        visitor.visit_char('a')
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.too_deep() {
+            return visitor.visit_none();
+        }
+        let inner = RefCell::new(Schema::Any);
+        let probe = Probe {
+            slot: &inner,
+            path: self.path.clone(),
+            ..self
+        };
+        let result = visitor.visit_some(probe);
+        self.set(Schema::Optional(Box::new(inner.into_inner())));
+        result
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if name != secret::NAME {
+            return visitor.visit_newtype_struct(self);
+        }
+        let inner = RefCell::new(Schema::Any);
+        let probe = Probe {
+            slot: &inner,
+            path: self.path.clone(),
+            ..self
+        };
+        let result = visitor.visit_newtype_struct(probe);
+        self.set(Schema::Secret(Box::new(inner.into_inner())));
+        result
+    }
+
+    /// Hands out a single item.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_tuple(1, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        let len = if self.too_deep() { 0 } else { len };
+        let slots: Vec<_> = (0..len).map(|_| RefCell::new(Schema::Any)).collect();
+        let items = slots.iter().enumerate().map(|(i, slot)| {
+            let segment = i.to_string();
+            let probe = self.child(&segment, slot);
+            (segment, None, probe)
+        });
+        let result = visitor.visit_seq(ProbeAccess::new(items.collect()));
+        // tuples are shown like lists of their first item
+        let item = slots
+            .into_iter()
+            .next()
+            .map_or(Schema::Any, RefCell::into_inner);
+        self.set(Schema::List(Box::new(item)));
+        result
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    /// Hands out a single entry.
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = RefCell::new(Schema::Any);
+        let mut entries = Vec::new();
+        if !self.too_deep() {
+            entries.push(("*".to_string(), None, self.child("*", &value)));
+        }
+        let result = visitor.visit_map(ProbeAccess::new(entries));
+        self.set(Schema::Map(Box::new(value.into_inner())));
+        result
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let slots: Vec<_> = fields.iter().map(|_| RefCell::new(Schema::Any)).collect();
+        let mut entries = Vec::new();
+        for (f, slot) in fields.iter().zip(&slots) {
+            let probe = self.child(f, slot);
+            if self.pass.omit.as_ref() != Some(&probe.path) {
+                entries.push((f.to_string(), Some(*f), probe));
+            }
+        }
+        // fields that failed in an earlier pass go last, so the others are probed first
+        entries.sort_by_key(|(_, _, probe)| self.failed(&probe.path));
+        let result = visitor.visit_map(ProbeAccess::new(entries));
+        let fields = fields.iter().zip(slots).map(|(name, slot)| SchemaField {
+            name,
+            schema: slot.into_inner(),
+            required: false,
+        });
+        self.set(Schema::Struct(fields.collect()));
+        result
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let selected = variants.iter().position(|v| {
+            let path = join(&self.path, v);
+            self.pass
+                .select
+                .as_ref()
+                .is_some_and(|s| *s == path || s.starts_with(&format!("{path}.")))
+        });
+        let slots: Vec<_> = variants.iter().map(|_| RefCell::new(Schema::Any)).collect();
+        let result = match variants.get(selected.unwrap_or(0)) {
+            Some(name) => {
+                let probe = self.child(name, &slots[selected.unwrap_or(0)]);
+                visitor.visit_enum(ProbeVariant { name, probe })
+            }
+            None => visitor.visit_enum(PlaceholderVariant("")),
+        };
+        let variants = variants.iter().zip(slots).map(|(name, slot)| Variant {
+            name,
+            schema: slot.into_inner(),
+        });
+        self.set(Schema::Enum(variants.collect()));
+        result
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// The variant `name` of an enum, with the probe of its data.
+struct ProbeVariant<'a> {
+    name: &'static str,
+    probe: Probe<'a>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for ProbeVariant<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self), Error> {
+        let variant = seed.deserialize(self.name.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for ProbeVariant<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        self.probe.set(Schema::Struct(Vec::new()));
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(self.probe)
+            .map_err(|e| e.prefixed(self.name))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.probe
+            .deserialize_tuple(len, visitor)
+            .map_err(|e| e.prefixed(self.name))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.probe
+            .deserialize_struct("", fields, visitor)
+            .map_err(|e| e.prefixed(self.name))
+    }
+}
+
+/// Hands out the fields of a struct, the entry of a map or the items of a sequence.
+struct ProbeAccess<'a> {
+    /// The segment of the path, the name of a struct field or `None` for map keys, and the probe
+    /// of the value.
+    entries: std::vec::IntoIter<(String, Option<&'static str>, Probe<'a>)>,
+    value: Option<(String, Probe<'a>)>,
+}
+
+impl<'a> ProbeAccess<'a> {
+    fn new(entries: Vec<(String, Option<&'static str>, Probe<'a>)>) -> Self {
+        ProbeAccess {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for ProbeAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((segment, field, probe)) = self.entries.next() else {
+            return Ok(None);
+        };
+        let key = match field {
+            Some(field) => seed.deserialize(field.into_deserializer()),
+            None => seed.deserialize(Placeholder),
+        };
+        self.value = Some((segment, probe));
+        key.map(Some)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Error> {
+        let (segment, probe) = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(probe).map_err(|e| e.prefixed(&segment))
+    }
+}
+
+impl<'de, 'a> SeqAccess<'de> for ProbeAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Error> {
+        match self.entries.next() {
+            Some((segment, _, probe)) => seed
+                .deserialize(probe)
+                .map(Some)
+                .map_err(|e| e.prefixed(&segment)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Schema {
+    /// A short name of the type, like `u16`, `list<string>` or `a|b`.
+    pub fn type_name(&self) -> String {
+        match self {
+            Schema::Any => "any".into(),
+            Schema::Scalar(name) => name.to_string(),
+            Schema::Optional(inner) | Schema::Secret(inner) => inner.type_name(),
+            Schema::List(inner) => format!("list<{}>", inner.type_name()),
+            Schema::Map(inner) => format!("map<{}>", inner.type_name()),
+            Schema::Struct(_) => "struct".into(),
+            Schema::Enum(variants) => {
+                let names: Vec<_> = variants.iter().map(|v| v.name).collect();
+                names.join("|")
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{Deserializer, Visitor},
+    Deserialize,
+};
+use zeroize::Zeroize;
+
+/// The newtype name [`Secret`] deserializes as, so errors can leave out its value.
+pub(crate) const NAME: &str = "from_env::Secret";
+
+/// A value that is kept out of logs: `Debug` and `Display` print `***`, and errors about its value
+/// leave the value out. It is read with [`Secret::expose`] and zeroed in memory when dropped.
+///
+/// Deserializes like `T`, so it can wrap any field:
+///
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use from_env::{FromEnv, Secret};
+/// #[derive(Debug, serde::Deserialize, FromEnv)]
+/// struct Constants {
+///     api_key: Secret<String>,
+/// }
+///
+/// let constants = Constants::from_args(["--api_key", "hunter2"])?;
+/// assert_eq!(format!("{constants:?}"), "Constants { api_key: *** }");
+/// assert_eq!(constants.api_key.expose(), "hunter2");
+/// # Ok::<(), from_env::Error>(())
+/// ```
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// The secret value. Take care not to log it.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret(value)
+    }
+}
+
+impl<T: Zeroize + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Secret(self.0.clone())
+    }
+}
+
+impl<T: Zeroize + Default> Default for Secret<T> {
+    fn default() -> Self {
+        Secret(T::default())
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<T: Zeroize> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<'de, T: Deserialize<'de> + Zeroize> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(NAME, SecretVisitor(PhantomData))
+    }
+}
+
+struct SecretVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de> + Zeroize> Visitor<'de> for SecretVisitor<T> {
+    type Value = Secret<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a secret value")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Secret<T>, D::Error> {
+        T::deserialize(deserializer).map(Secret)
+    }
+}
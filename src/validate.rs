@@ -0,0 +1,316 @@
+//! Checks of values after deserializing: declarative [`Constraint`]s on fields, [`Relation`]s
+//! between fields, and the [`Validate`] hook for anything else.
+
+use std::path::Path;
+
+use crate::{
+    de::parse_bool,
+    node::{Node, Raw},
+    schema::Schema,
+    Error, Field, Options, Origin,
+};
+
+/// A check of the raw value of a field, given with `#[from_env(...)]`. For lists, each item is
+/// checked.
+///
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use from_env::FromEnv;
+/// #[derive(Debug, serde::Deserialize, FromEnv)]
+/// struct Constants {
+///     #[from_env(min = 1, max = 65535)]
+///     port: u16,
+///     #[from_env(one_of("debug", "info", "warn"))]
+///     log_level: String,
+/// }
+///
+/// let e = Constants::from_args(["--port", "0", "--log_level", "info"]).unwrap_err();
+/// assert_eq!(e.to_string(), r#"invalid value "0" for `port` (CLI arg --port): must be at least 1"#);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Constraint {
+    /// `min = 1`: a number of at least this.
+    Min(f64),
+    /// `max = 65535`: a number of at most this.
+    Max(f64),
+    /// `non_empty`: not an empty string, or for lists, at least one item.
+    NonEmpty,
+    /// `regex = "[a-z]+"`: the whole value matching the pattern, which needs the `regex` feature.
+    Regex(&'static str),
+    /// `one_of("debug", "info")`: one of the values.
+    OneOf(&'static [&'static str]),
+    /// `path_exists`: a path to an existing file or directory.
+    PathExists,
+}
+
+/// A relation of a field to another one, given with `#[from_env(...)]` and checked across all
+/// sources. Fields are named as serde sees them.
+///
+/// A field counts as given if a source other than its default gives a value, which for bools has
+/// to be true, so `--no-tls` does not require anything. Errors name both fields and where their
+/// values came from.
+///
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use from_env::FromEnv;
+/// #[derive(Debug, serde::Deserialize, FromEnv)]
+/// struct Constants {
+///     #[from_env(default = "false")]
+///     tls: bool,
+///     #[from_env(required_if("tls", "true"))]
+///     cert_file: Option<String>,
+///     #[from_env(conflicts_with = "server_url")]
+///     socket: Option<String>,
+///     server_url: Option<String>,
+/// }
+///
+/// let e = Constants::from_args(["--tls", "true"]).unwrap_err();
+/// assert_eq!(
+///     e.to_string(),
+///     r#"missing value for `cert_file`, required because `tls` is "true" (CLI arg --tls)"#
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Relation {
+    /// `requires = "cert_file"` or `requires("cert_file", "key_file")`: if this field is given,
+    /// the other one needs a value too, which may be its default.
+    Requires(&'static str),
+    /// `conflicts_with = "server_url"`: this field and the other one are not given together.
+    ConflictsWith(&'static str),
+    /// `required_if("tls", "true")`: this field needs a value if the other one has the given
+    /// value, which may be its default. Booleans are compared by what they parse to.
+    RequiredIf(&'static str, &'static str),
+}
+
+/// A check of the whole value after deserializing, run if the type is derived with
+/// `#[from_env(validate)]`. Errors are returned like parse errors.
+///
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use from_env::{Error, FromEnv, Validate};
+/// #[derive(serde::Deserialize, FromEnv)]
+/// #[from_env(validate)]
+/// struct Constants {
+///     min_workers: u32,
+///     max_workers: u32,
+/// }
+///
+/// impl Validate for Constants {
+///     fn validate(&self) -> Result<(), Error> {
+///         if self.min_workers > self.max_workers {
+///             return Err(Error::Custom {
+///                 key: "min_workers".to_string(),
+///                 message: "must not be greater than `max_workers`".to_string(),
+///             });
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let e = Constants::from_args(["--min_workers", "8", "--max_workers", "4"]).err().unwrap();
+/// assert_eq!(e.to_string(), "`min_workers`: must not be greater than `max_workers`");
+/// ```
+pub trait Validate {
+    fn validate(&self) -> Result<(), Error>;
+}
+
+/// Checks the [`Relation`]s and [`Constraint`]s of `fields` against their values in `node`. Fields
+/// without value are left to deserializing.
+pub(crate) fn check(
+    node: &Node,
+    schema: &Schema,
+    fields: &[Field],
+    options: &Options,
+) -> Vec<Error> {
+    let mut errors = Vec::new();
+    for field in fields {
+        check_relations(node, schema, field, &mut errors);
+    }
+    for field in fields.iter().filter(|f| !f.constraints.is_empty()) {
+        let (Some(node), Some(schema)) = (node.get([field.name]), schema.get(field.name)) else {
+            continue;
+        };
+        let Some(given) = node.find_value(&|_| true) else {
+            continue;
+        };
+        let items = items(node, schema, options);
+        let invalid = |value: String, origin: Origin, message: String| {
+            let e = Error::Invalid {
+                key: field.name.to_string(),
+                value,
+                origin,
+                message,
+            };
+            if field.secret || schema.is_secret() {
+                e.redacted()
+            } else {
+                e
+            }
+        };
+        for constraint in field.constraints {
+            if *constraint == Constraint::NonEmpty && items.is_empty() {
+                let message = constraint.violation();
+                errors.push(invalid(String::new(), given.origin.clone(), message));
+                continue;
+            }
+            for (value, origin) in &items {
+                match constraint.holds(value) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        let message = constraint.violation();
+                        errors.push(invalid(value.clone(), origin.clone(), message));
+                    }
+                    Err(message) => errors.push(Error::Custom {
+                        key: field.name.to_string(),
+                        message,
+                    }),
+                }
+            }
+        }
+    }
+    errors
+}
+
+fn check_relations(node: &Node, schema: &Schema, field: &Field, errors: &mut Vec<Error>) {
+    // any value, including the default
+    let value = |name: &str| node.get([name])?.find_value(&|_| true);
+    let given = |name: &str| {
+        let node = node.get([name])?;
+        if schema.get(name).is_some_and(Schema::is_bool) {
+            // a bool is only given if it is turned on
+            return node.value().filter(|raw| {
+                raw.origin != Origin::Default && parse_bool(&raw.value) == Some(true)
+            });
+        }
+        node.find_value(&|raw| raw.origin != Origin::Default)
+    };
+    for relation in field.relations {
+        let error = match *relation {
+            Relation::Requires(other) => match (given(field.name), value(other)) {
+                (Some(raw), None) => Error::Required {
+                    key: other.to_string(),
+                    by: field.name.to_string(),
+                    value: None,
+                    origin: raw.origin.clone(),
+                },
+                _ => continue,
+            },
+            Relation::ConflictsWith(other) => match (given(field.name), given(other)) {
+                (Some(raw), Some(other_raw)) => Error::Conflict {
+                    key: field.name.to_string(),
+                    origin: raw.origin.clone(),
+                    other: other.to_string(),
+                    other_origin: Box::new(other_raw.origin.clone()),
+                },
+                _ => continue,
+            },
+            Relation::RequiredIf(other, expected) => match (value(field.name), value(other)) {
+                (None, Some(raw)) if same_value(&raw.value, expected) => Error::Required {
+                    key: field.name.to_string(),
+                    by: other.to_string(),
+                    value: Some(expected.to_string()),
+                    origin: raw.origin.clone(),
+                },
+                _ => continue,
+            },
+        };
+        // a conflict declared on both fields is reported once
+        let reported = errors.iter().any(|e| match (e, &error) {
+            (
+                Error::Conflict { key, other, .. },
+                Error::Conflict {
+                    key: new_key,
+                    other: new_other,
+                    ..
+                },
+            ) => key == new_other && other == new_key,
+            _ => false,
+        });
+        if !reported {
+            errors.push(error);
+        }
+    }
+}
+
+fn same_value(value: &str, expected: &str) -> bool {
+    match (parse_bool(value), parse_bool(expected)) {
+        (Some(value), Some(expected)) => value == expected,
+        _ => value == expected,
+    }
+}
+
+/// The values a field was given, one per item for lists.
+fn items(node: &Node, schema: &Schema, options: &Options) -> Vec<(String, Origin)> {
+    let item = |raw: &Raw| (raw.value.clone(), raw.origin.clone());
+    if !matches!(schema.unwrap_optional(), Schema::List(_)) {
+        return node.value().map(item).into_iter().collect();
+    }
+    if !node.children.is_empty() {
+        return node
+            .children
+            .values()
+            .filter_map(Node::value)
+            .map(item)
+            .collect();
+    }
+    node.values
+        .iter()
+        .filter(|raw| !raw.value.is_empty())
+        .flat_map(|raw| {
+            raw.value
+                .split(options.list_delimiter)
+                .map(|value| (value.to_string(), raw.origin.clone()))
+        })
+        .collect()
+}
+
+impl Constraint {
+    /// Whether `value` satisfies the constraint, or why it cannot be checked.
+    fn holds(&self, value: &str) -> Result<bool, String> {
+        let number = || {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("expected a number, found {value:?}"))
+        };
+        Ok(match *self {
+            Constraint::Min(min) => number()? >= min,
+            Constraint::Max(max) => number()? <= max,
+            Constraint::NonEmpty => !value.is_empty(),
+            Constraint::Regex(pattern) => matches(pattern, value)?,
+            Constraint::OneOf(values) => values.contains(&value),
+            Constraint::PathExists => Path::new(value).exists(),
+        })
+    }
+
+    fn violation(&self) -> String {
+        match self {
+            Constraint::Min(min) => format!("must be at least {min}"),
+            Constraint::Max(max) => format!("must be at most {max}"),
+            Constraint::NonEmpty => "must not be empty".to_string(),
+            Constraint::Regex(pattern) => format!("must match `{pattern}`"),
+            Constraint::OneOf(values) => {
+                let values: Vec<_> = values.iter().map(|v| format!("`{v}`")).collect();
+                format!("must be one of {}", values.join(", "))
+            }
+            Constraint::PathExists => "path does not exist".to_string(),
+        }
+    }
+}
+
+/// Whether the whole of `value` matches `pattern`.
+#[cfg(feature = "regex")]
+fn matches(pattern: &str, value: &str) -> Result<bool, String> {
+    let regex = regex::Regex::new(&format!("^(?:{pattern})$"))
+        .map_err(|e| format!("invalid regex `{pattern}`: {e}"))?;
+    Ok(regex.is_match(value))
+}
+
+#[cfg(not(feature = "regex"))]
+fn matches(pattern: &str, _value: &str) -> Result<bool, String> {
+    Err(format!(
+        "checking regex `{pattern}` needs the `regex` feature of from_env"
+    ))
+}
@@ -0,0 +1,201 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, PoisonError, RwLock, Weak,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{schema::Schema, Error, FromEnv, FromEnvBuilder};
+
+/// A value that is read again when its `.env` or config files change, created by
+/// [`FromEnvBuilder::watch`]. With the `sighup` feature, `SIGHUP` makes it read again too.
+///
+/// If reading fails, the previous value is kept. Clones share the value.
+///
+#[cfg_attr(feature = "derive", doc = "```no_run")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use from_env::FromEnv;
+/// # use std::time::Duration;
+/// # #[derive(serde::Deserialize, FromEnv)]
+/// # struct Constants { server_url: String }
+/// let constants = Constants::builder().watch(Duration::from_secs(2))?;
+/// let changes = constants.subscribe();
+/// std::thread::spawn(move || {
+///     for change in changes {
+///         if let Err(e) = change {
+///             eprintln!("keeping the previous config: {e}");
+///         }
+///     }
+/// });
+/// // a snapshot, which stays the same while it is used
+/// let server_url = &constants.get().server_url;
+/// # Ok::<(), from_env::Error>(())
+/// ```
+pub struct Watched<T> {
+    shared: Arc<Shared<T>>,
+}
+
+struct Shared<T> {
+    builder: FromEnvBuilder<T>,
+    schema: Schema,
+    current: RwLock<Arc<T>>,
+    subscribers: Mutex<Vec<Subscriber<T>>>,
+}
+
+type Subscriber<T> = mpsc::Sender<Result<Arc<T>, Error>>;
+
+impl<T: FromEnv + Send + Sync + 'static> Watched<T> {
+    pub(crate) fn new(builder: FromEnvBuilder<T>, interval: Duration) -> Result<Self, Error> {
+        let hangup = hangup()?;
+        let schema = Schema::of::<T>();
+        let stamp = stamp(&watched_files(&builder, &schema));
+        let value = builder.build()?;
+        let shared = Arc::new(Shared {
+            builder,
+            schema,
+            current: RwLock::new(Arc::new(value)),
+            subscribers: Mutex::new(Vec::new()),
+        });
+        let weak = Arc::downgrade(&shared);
+        thread::spawn(move || poll(weak, interval, stamp, hangup));
+        Ok(Watched { shared })
+    }
+
+    /// The current value. This is cheap, so call it each time instead of keeping the value.
+    pub fn get(&self) -> Arc<T> {
+        let current = self.shared.current.read();
+        current.unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Reads the sources again. If that fails, the previous value is kept and the error returned.
+    pub fn reload(&self) -> Result<Arc<T>, Error> {
+        self.shared.reload()
+    }
+
+    /// Receives each value read again, or the error if reading failed.
+    pub fn subscribe(&self) -> mpsc::Receiver<Result<Arc<T>, Error>> {
+        let (tx, rx) = mpsc::channel();
+        let subscribers = self.shared.subscribers.lock();
+        subscribers.unwrap_or_else(PoisonError::into_inner).push(tx);
+        rx
+    }
+}
+
+impl<T> Clone for Watched<T> {
+    fn clone(&self) -> Self {
+        Watched {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T: FromEnv> Shared<T> {
+    fn reload(&self) -> Result<Arc<T>, Error> {
+        let result = self.builder.build().map(Arc::new);
+        if let Ok(value) = &result {
+            let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+            *current = value.clone();
+        }
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        subscribers.retain(|tx| tx.send(result.clone()).is_ok());
+        result
+    }
+}
+
+/// Checks the files every `interval` until all [`Watched`] are dropped.
+fn poll<T: FromEnv>(
+    shared: Weak<Shared<T>>,
+    interval: Duration,
+    mut seen: Vec<Stamp>,
+    hangup: Hangup,
+) {
+    loop {
+        thread::sleep(interval);
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        // taken first, so a signal in a tick where the files changed too does not linger
+        let hangup = hangup.received();
+        let stamp = stamp(&watched_files(&shared.builder, &shared.schema));
+        if hangup || stamp != seen {
+            // the error goes to the subscribers
+            let _ = shared.reload();
+        }
+        seen = stamp;
+    }
+}
+
+/// A file with its modification time and length, `None` if it cannot be read.
+type Stamp = (PathBuf, Option<(SystemTime, u64)>);
+
+/// The `.env` files and the config file, which may change between calls.
+fn watched_files<T: FromEnv>(builder: &FromEnvBuilder<T>, schema: &Schema) -> Vec<PathBuf> {
+    let options = builder.options();
+    let mut files = crate::config_file(options, T::fields(), schema)
+        .into_iter()
+        .collect::<Vec<_>>();
+    if options.dotenv_content.is_none() {
+        files.extend(crate::dotenv_files(options, T::fields(), schema).unwrap_or_default());
+    }
+    files
+}
+
+fn stamp(files: &[PathBuf]) -> Vec<Stamp> {
+    files
+        .iter()
+        .map(|path| {
+            let meta = fs::metadata(path).ok();
+            let stamp = meta.and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+            (path.clone(), stamp)
+        })
+        .collect()
+}
+
+/// A flag set when the process receives `SIGHUP`. Its handler is removed when it is dropped, so
+/// handlers do not pile up as [`Watched`] values come and go.
+struct Hangup {
+    flag: Arc<AtomicBool>,
+    #[cfg(all(feature = "sighup", unix))]
+    id: signal_hook::SigId,
+}
+
+impl Hangup {
+    /// Whether `SIGHUP` was received since the last call.
+    fn received(&self) -> bool {
+        self.flag.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[cfg(all(feature = "sighup", unix))]
+impl Drop for Hangup {
+    fn drop(&mut self) {
+        signal_hook::low_level::unregister(self.id);
+    }
+}
+
+#[cfg(all(feature = "sighup", unix))]
+fn hangup() -> Result<Hangup, Error> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let id =
+        signal_hook::flag::register(signal_hook::consts::SIGHUP, flag.clone()).map_err(|e| {
+            Error::Custom {
+                key: String::new(),
+                message: format!("could not listen for SIGHUP: {e}"),
+            }
+        })?;
+    Ok(Hangup { flag, id })
+}
+
+#[cfg(not(all(feature = "sighup", unix)))]
+fn hangup() -> Result<Hangup, Error> {
+    Ok(Hangup {
+        flag: Arc::new(AtomicBool::new(false)),
+    })
+}
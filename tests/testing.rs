@@ -0,0 +1,595 @@
+//! The entry points for tests, which read only the values they are given.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use from_env::{FromEnv, Origin, Source};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, FromEnv)]
+struct Constants {
+    server_url: String,
+    #[from_env(short = 'p', default = "8080")]
+    port: u16,
+    database: Database,
+}
+
+#[derive(Debug, Deserialize)]
+struct Database {
+    host: String,
+}
+
+#[test]
+fn from_args() {
+    let constants =
+        Constants::from_args(["--server_url", "x", "-p", "80", "--database.host", "db"]).unwrap();
+    assert_eq!(constants.server_url, "x");
+    assert_eq!(constants.port, 80);
+    assert_eq!(constants.database.host, "db");
+}
+
+#[test]
+fn from_dotenv_str() {
+    let constants = Constants::from_dotenv_str("SERVER_URL=x\nDATABASE__HOST=db").unwrap();
+    assert_eq!(constants.server_url, "x");
+    assert_eq!(constants.port, 8080);
+    assert_eq!(constants.database.host, "db");
+}
+
+#[test]
+fn from_map() {
+    let vars = BTreeMap::from([
+        ("SERVER_URL".to_string(), "x".to_string()),
+        ("DATABASE__HOST".to_string(), "db".to_string()),
+        ("PORT".to_string(), "80".to_string()),
+    ]);
+    let constants = Constants::from_map(vars).unwrap();
+    assert_eq!(constants.server_url, "x");
+    assert_eq!(constants.port, 80);
+    assert_eq!(constants.database.host, "db");
+}
+
+#[test]
+fn errors_name_the_source() {
+    let e = Constants::from_dotenv_str("SERVER_URL=x\nPORT=80a").unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "2 errors:\n  invalid value \"80a\" for `port` (.env line 2): expected u16\n  missing value for `database`"
+    );
+}
+
+#[test]
+fn expansion_ignores_the_environment_of_the_process() {
+    std::env::set_var("FROM_ENV_TEST_HOST", "real");
+    let constants =
+        Constants::from_dotenv_str("SERVER_URL=$HOME/api\nDATABASE__HOST=$FROM_ENV_TEST_HOST")
+            .unwrap();
+    assert_eq!(constants.server_url, "/api");
+    assert_eq!(constants.database.host, "");
+}
+
+#[test]
+fn expansion_uses_the_given_env_vars() {
+    let constants = Constants::builder()
+        .sources([Source::Dotenv, Source::Env])
+        .env_vars([("HOME", "fake")])
+        .dotenv_str("SERVER_URL=$HOME/x\nDATABASE__HOST=${DB_HOST:-localhost}")
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "fake/x");
+    assert_eq!(constants.database.host, "localhost");
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, FromEnv)]
+struct Tls {
+    #[from_env(default = "false", requires("key_file"))]
+    tls: bool,
+    key_file: Option<String>,
+    #[from_env(conflicts_with = "tls")]
+    socket: Option<String>,
+}
+
+#[test]
+fn bools_turned_off_are_not_given() {
+    for args in [&["--tls", "false"][..], &["--no-tls"], &[]] {
+        let constants = Tls::from_args(args.iter().copied()).unwrap();
+        assert!(!constants.tls);
+    }
+    Tls::from_dotenv_str("TLS=false").unwrap();
+    Tls::from_args(["--no-tls", "--socket", "/s"]).unwrap();
+    Tls::from_args(["--tls", "false", "--socket", "/s"]).unwrap();
+}
+
+#[test]
+fn bools_turned_on_are_given() {
+    let e = Tls::from_args(["--tls"]).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "missing value for `key_file`, required by `tls` (CLI arg --tls)"
+    );
+    let e = Tls::from_dotenv_str("TLS=true\nKEY_FILE=k\nSOCKET=/s").unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "`socket` (.env line 3) conflicts with `tls` (.env line 1)"
+    );
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Command {
+    Serve {
+        port: u16,
+    },
+    Migrate {
+        #[serde(default)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Deserialize, FromEnv)]
+struct Cli {
+    #[from_env(subcommand)]
+    command: Command,
+    #[from_env(positional)]
+    files: Vec<String>,
+}
+
+#[test]
+fn positional_sequences_may_be_empty() {
+    let cli = Cli::from_args(["serve", "--port", "80"]).unwrap();
+    assert_eq!(cli.command, Command::Serve { port: 80 });
+    assert!(cli.files.is_empty());
+    let cli = Cli::from_args(["migrate", "--dry_run", "a.sql", "b.sql"]).unwrap();
+    assert_eq!(cli.command, Command::Migrate { dry_run: true });
+    assert_eq!(cli.files, ["a.sql", "b.sql"]);
+    let usage = Cli::builder().usage();
+    assert!(usage.contains("<command> [<files>...]"), "{usage}");
+    let files = usage
+        .lines()
+        .find(|l| l.contains("[<files>...]  "))
+        .unwrap();
+    assert!(!files.contains("required"), "{files}");
+}
+
+#[test]
+fn env_template_keys_have_no_prefix() {
+    let builder = Constants::builder()
+        .sources([Source::Dotenv])
+        .env_prefix("APP_")
+        .strict(true);
+    let template = builder.env_template();
+    assert!(template.contains("\nSERVER_URL=\n"), "{template}");
+    assert!(template.contains("\n# PORT=8080\n"), "{template}");
+    assert!(!template.contains("APP_"), "{template}");
+    // the filled in template is read back
+    let filled = template
+        .replace("SERVER_URL=", "SERVER_URL=x")
+        .replace("DATABASE__HOST=", "DATABASE__HOST=db");
+    let constants = builder.dotenv_str(filled).build().unwrap();
+    assert_eq!(constants.server_url, "x");
+    assert_eq!(constants.database.host, "db");
+}
+
+#[derive(Debug, Deserialize, FromEnv)]
+struct Renamed {
+    #[from_env(env = "DB_URL", long = "db")]
+    database_url: String,
+}
+
+/// A file in the temp dir with `content`, named after the test.
+fn temp_file(name: &str, content: &str) -> String {
+    let path = std::env::temp_dir().join(format!("from_env_{name}"));
+    fs::write(&path, content).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn key_files_of_renamed_fields() {
+    let path = temp_file("key_files_of_renamed_fields", "postgres://db\n");
+    let vars = [("DB_URL_FILE", path.as_str())];
+    let renamed = Renamed::builder()
+        .sources([Source::Env])
+        .env_vars(vars)
+        .env_prefix("APP_")
+        .build()
+        .unwrap();
+    assert_eq!(renamed.database_url, "postgres://db");
+    let renamed = Renamed::from_args(["--db-file", &path]).unwrap();
+    assert_eq!(renamed.database_url, "postgres://db");
+
+    let vars = BTreeMap::from([("DATABASE_URL_FILE".to_string(), path.clone())]);
+    let e = Renamed::from_map(vars).unwrap_err();
+    assert_eq!(e.to_string(), "missing value for `database_url`");
+    let e = Renamed::from_args(["--database_url-file", &path]).unwrap_err();
+    assert_eq!(e.to_string(), "missing value for `database_url`");
+}
+
+#[test]
+fn strict_rejects_old_names_of_renamed_fields() {
+    let e = Renamed::builder()
+        .sources([Source::Dotenv])
+        .dotenv_str("DATABASE_URL=x")
+        .strict(true)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "unknown key `DATABASE_URL` (.env line 1), did you mean `DB_URL`?"
+    );
+    let e = Renamed::builder()
+        .args(["--db", "x", "--database_url", "y"])
+        .help(false)
+        .strict(true)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "unknown key `database_url` (CLI arg --database_url), did you mean `db`?"
+    );
+    let e = Renamed::builder()
+        .sources([Source::Env])
+        .env_vars([("DB_URL", "x"), ("APP_DATABASE_URL_FILE", "/y")])
+        .env_prefix("APP_")
+        .strict(true)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "unknown key `APP_DATABASE_URL_FILE` (env var APP_DATABASE_URL_FILE), did you mean `DB_URL_FILE`?"
+    );
+    // without a prefix, other variables are not checked
+    Renamed::builder()
+        .sources([Source::Env])
+        .env_vars([("DB_URL", "x"), ("DATABASE_URL", "y")])
+        .strict(true)
+        .build()
+        .unwrap();
+}
+
+#[test]
+fn variables_configuring_the_sources_are_not_keys() {
+    let vars = [
+        ("APP_ENV", "production"),
+        ("DOTENV_PATH", "/nonexistent/.env"),
+        ("APP_SERVER_URL", "x"),
+        ("APP_DATABASE__HOST", "db"),
+    ];
+    let constants = Constants::builder()
+        .sources([Source::Env])
+        .env_vars(vars)
+        .env_prefix("APP_")
+        .strict(true)
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "x");
+
+    #[derive(Deserialize, FromEnv)]
+    struct Stage {
+        app_env: String,
+    }
+    let stage = Stage::from_map(BTreeMap::from([("APP_ENV".into(), "production".into())]));
+    assert_eq!(stage.unwrap().app_env, "production");
+}
+
+#[test]
+fn empty_dotenv_values_are_skipped() {
+    #[derive(Deserialize, FromEnv)]
+    struct Defaults {
+        #[serde(default)]
+        port: u16,
+        #[from_env(default = "x")]
+        server_url: String,
+    }
+    let defaults = Defaults::from_dotenv_str("PORT=\nSERVER_URL=").unwrap();
+    assert_eq!(defaults.port, 0);
+    assert_eq!(defaults.server_url, "x");
+}
+
+#[test]
+fn single_quoted_values_are_literal() {
+    let src = "SERVER_URL='pa$word'\nDATABASE__HOST=\"$SERVER_URL\"";
+    let constants = Constants::from_dotenv_str(src).unwrap();
+    assert_eq!(constants.server_url, "pa$word");
+    assert_eq!(constants.database.host, "pa$word");
+    let constants = Constants::builder()
+        .sources([Source::Dotenv])
+        .dotenv_str(src)
+        .expand_single_quoted(true)
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "pa");
+}
+
+/// An empty directory for the files of one test.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("from_env_{name}"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn watched_values_follow_file_changes() {
+    let path = temp_dir("watched_values_follow_file_changes").join(".env");
+    fs::write(&path, "SERVER_URL=a\nDATABASE__HOST=h\n").unwrap();
+    let watched = Constants::builder()
+        .sources([Source::Dotenv])
+        .dotenv(&path)
+        .watch(Duration::from_millis(10))
+        .unwrap();
+    let changes = watched.subscribe();
+    assert_eq!(watched.get().server_url, "a");
+    fs::write(&path, "SERVER_URL=changed\nDATABASE__HOST=h\n").unwrap();
+    let change = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(change.unwrap().server_url, "changed");
+    assert_eq!(watched.get().server_url, "changed");
+}
+
+#[test]
+fn watched_values_are_kept_when_reading_fails() {
+    let path = temp_dir("watched_values_are_kept_when_reading_fails").join(".env");
+    fs::write(&path, "SERVER_URL=a\nDATABASE__HOST=h\n").unwrap();
+    let watched = Constants::builder()
+        .sources([Source::Dotenv])
+        .dotenv(&path)
+        .watch(Duration::from_millis(10))
+        .unwrap();
+    let changes = watched.subscribe();
+    fs::write(&path, "SERVER_URL=b\nDATABASE__HOST=h\nPORT=x\n").unwrap();
+    let change = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(change.unwrap_err().key(), Some("port"));
+    assert_eq!(watched.get().server_url, "a");
+    assert_eq!(watched.reload().unwrap_err().key(), Some("port"));
+    assert_eq!(watched.get().server_url, "a");
+}
+
+#[test]
+fn reloading_notifies_all_subscribers() {
+    let path = temp_dir("reloading_notifies_all_subscribers").join(".env");
+    fs::write(&path, "SERVER_URL=a\nDATABASE__HOST=h\n").unwrap();
+    let watched = Constants::builder()
+        .sources([Source::Dotenv])
+        .dotenv(&path)
+        .watch(Duration::from_secs(3600))
+        .unwrap();
+    let first = watched.subscribe();
+    let second = watched.clone().subscribe();
+    fs::write(&path, "SERVER_URL=b\nDATABASE__HOST=h\n").unwrap();
+    assert_eq!(watched.reload().unwrap().server_url, "b");
+    assert_eq!(first.try_recv().unwrap().unwrap().server_url, "b");
+    assert_eq!(second.try_recv().unwrap().unwrap().server_url, "b");
+    // dropped subscribers do not stop the others
+    drop(first);
+    assert!(watched.reload().is_ok());
+    assert!(second.try_recv().unwrap().is_ok());
+}
+
+/// Writes each file into `dir`, by name and content.
+fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    for (name, content) in files {
+        fs::write(dir.join(name), content).unwrap();
+    }
+}
+
+#[test]
+fn dotenv_layers_override_in_order() {
+    let dir = temp_dir("dotenv_layers_override_in_order");
+    write_files(
+        &dir,
+        &[
+            (".env", "SERVER_URL=base\nDATABASE__HOST=base\nPORT=1"),
+            (".env.local", "DATABASE__HOST=local\nPORT=2"),
+            (".env.production", "SERVER_URL=production\nPORT=3"),
+            (".env.production.local", "PORT=4"),
+            (".env.staging", "SERVER_URL=staging"),
+        ],
+    );
+    let builder = Constants::builder()
+        .sources([Source::Dotenv])
+        .dotenv(dir.join(".env"))
+        .dotenv_layers(true)
+        .app_env("production");
+    let names: Vec<_> = builder
+        .dotenv_files()
+        .unwrap()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ".env",
+            ".env.local",
+            ".env.production",
+            ".env.production.local"
+        ]
+    );
+    let constants = builder.build().unwrap();
+    assert_eq!(constants.server_url, "production");
+    assert_eq!(constants.database.host, "local");
+    assert_eq!(constants.port, 4);
+}
+
+#[test]
+fn dotenv_layers_are_selected_by_app_env() {
+    let dir = temp_dir("dotenv_layers_are_selected_by_app_env");
+    write_files(
+        &dir,
+        &[
+            (".env", "SERVER_URL=base\nDATABASE__HOST=base"),
+            (".env.staging", "SERVER_URL=staging"),
+        ],
+    );
+    let constants = Constants::builder()
+        .sources([Source::Dotenv])
+        .dotenv(dir.join(".env"))
+        .dotenv_layers(true)
+        .env_vars([("APP_ENV", "staging")])
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "staging");
+}
+
+#[test]
+fn dotenv_layers_apply_without_base_file() {
+    let dir = temp_dir("dotenv_layers_apply_without_base_file");
+    write_files(
+        &dir,
+        &[(".env.local", "SERVER_URL=local\nDATABASE__HOST=h")],
+    );
+    let constants = Constants::builder()
+        .sources([Source::Dotenv])
+        .dotenv(dir.join(".env"))
+        .dotenv_layers(true)
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "local");
+}
+
+#[test]
+fn dotenv_layers_are_off_by_default() {
+    let dir = temp_dir("dotenv_layers_are_off_by_default");
+    write_files(
+        &dir,
+        &[
+            (".env", "SERVER_URL=base\nDATABASE__HOST=base"),
+            (".env.local", "SERVER_URL=local"),
+        ],
+    );
+    let constants = Constants::builder()
+        .sources([Source::Dotenv])
+        .dotenv(dir.join(".env"))
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "base");
+}
+
+#[test]
+fn key_files_hold_values() {
+    let path = temp_file("key_files_hold_values", "secret\r\n");
+    let vars = [("SERVER_URL_FILE", path.as_str()), ("DATABASE__HOST", "h")];
+    let constants = Constants::builder()
+        .sources([Source::Env])
+        .env_vars(vars)
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "secret");
+}
+
+#[test]
+fn key_files_override_values_of_earlier_sources() {
+    let path = temp_file("key_files_override_values_of_earlier_sources", "file\n");
+    let constants = Constants::builder()
+        .sources([Source::Dotenv, Source::Env, Source::Args])
+        .dotenv_str("SERVER_URL=dotenv\nDATABASE__HOST=h")
+        .env_vars([("SERVER_URL_FILE", path.as_str())])
+        .args(Vec::<String>::new())
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "file");
+    let constants = Constants::builder()
+        .sources([Source::Dotenv, Source::Env, Source::Args])
+        .dotenv_str(format!("SERVER_URL_FILE={path}\nDATABASE__HOST=h"))
+        .env_vars([("SERVER_URL", "env")])
+        .args(Vec::<String>::new())
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "env");
+    let constants = Constants::builder()
+        .sources([Source::Dotenv, Source::Env, Source::Args])
+        .dotenv_str("DATABASE__HOST=h")
+        .env_vars([("SERVER_URL", "env")])
+        .args(["--server_url-file", path.as_str()])
+        .build()
+        .unwrap();
+    assert_eq!(constants.server_url, "file");
+}
+
+#[test]
+fn missing_key_files_are_invalid() {
+    let path = std::env::temp_dir().join("from_env_missing_key_files_are_invalid");
+    let _ = fs::remove_file(&path);
+    let e = Constants::builder()
+        .sources([Source::Env])
+        .env_vars([
+            ("SERVER_URL_FILE", path.to_str().unwrap()),
+            ("DATABASE__HOST", "h"),
+        ])
+        .build()
+        .unwrap_err();
+    match e {
+        from_env::Error::Invalid { key, message, .. } => {
+            assert_eq!(key, "server_url");
+            assert!(message.starts_with("could not read file"), "{message}");
+        }
+        e => panic!("expected an invalid value, got {e:?}"),
+    }
+}
+
+#[test]
+fn key_files_can_be_turned_off() {
+    let path = temp_file("key_files_can_be_turned_off", "file");
+    let e = Constants::builder()
+        .sources([Source::Env])
+        .env_vars([("SERVER_URL_FILE", path.as_str()), ("DATABASE__HOST", "h")])
+        .key_files(false)
+        .build()
+        .unwrap_err();
+    assert_eq!(e.key(), Some("server_url"));
+}
+
+#[test]
+fn provenance_lists_overridden_values() {
+    let (_, provenance) = Constants::builder()
+        .sources([Source::Dotenv, Source::Args])
+        .dotenv_str("SERVER_URL=x\nPORT=1\nDATABASE__HOST=h")
+        .args(["-p", "2"])
+        .build_with_report()
+        .unwrap();
+    let port = provenance.get("port").unwrap();
+    assert_eq!(port.value, "2");
+    assert!(
+        matches!(port.origin, Origin::Arg { index: 2, .. }),
+        "{port:?}"
+    );
+    let overridden: Vec<_> = port.overridden.iter().map(|(v, _)| v.as_str()).collect();
+    assert_eq!(overridden, ["8080", "1"]);
+    assert_eq!(port.overridden[0].1, Origin::Default);
+    let host = provenance.get("database.host").unwrap();
+    assert!(
+        matches!(host.origin, Origin::Dotenv { line: 3, .. }),
+        "{host:?}"
+    );
+    assert!(host.overridden.is_empty());
+}
+
+#[test]
+fn provenance_leaves_out_secrets() {
+    #[derive(Deserialize, FromEnv)]
+    #[allow(dead_code)]
+    struct Keys {
+        #[from_env(secret)]
+        api_key: String,
+        server_url: String,
+    }
+    let (_, provenance) = Keys::builder()
+        .sources([Source::Dotenv, Source::Env])
+        .dotenv_str("API_KEY=old-key\nSERVER_URL=x")
+        .env_vars([("API_KEY", "new-key")])
+        .build_with_report()
+        .unwrap();
+    let api_key = provenance.get("api_key").unwrap();
+    assert!(api_key.secret);
+    assert_eq!(api_key.value, "new-key");
+    for printed in [provenance.to_string(), format!("{provenance:?}")] {
+        assert!(!printed.contains("old-key"), "{printed}");
+        assert!(!printed.contains("new-key"), "{printed}");
+        assert!(printed.contains("***"), "{printed}");
+        assert!(printed.contains("\"x\""), "{printed}");
+    }
+}